@@ -35,6 +35,29 @@ impl State {
     pub fn trans_iter<'a>(&'a self) -> HashMapIterator<'a, u8, uint> {
         self.trans.iter()
     }
+
+    // the ranges of bytes this state has no transition for
+    fn dead_ranges(&self) -> ~[(u8, u8)] {
+        let mut ranges = ~[];
+        let mut c = 0u;
+
+        while c < 256 {
+            if self.trans.contains_key(&(c as u8)) {
+                c += 1;
+                continue;
+            }
+
+            let first = c;
+            while c + 1 < 256 && !self.trans.contains_key(&((c + 1) as u8)) {
+                c += 1;
+            }
+
+            ranges.push((first as u8, c as u8));
+            c += 1;
+        }
+
+        ranges
+    }
 }
 
 impl AutomataState for State {
@@ -87,6 +110,14 @@ impl Automata<State> for DFA {
 }
 
 impl DFA {
+    // the state reached from `st` by reading `c`, if any
+    pub fn transition(&self, st: uint, c: u8) -> Option<uint> {
+        match self.states.find(&st) {
+            Some(state) => state.trans.find(&c).map(|dst| *dst),
+            None => None
+        }
+    }
+
     // "determinization" of a NFA
     pub fn new_from_nfa(nfa: &::nfa::NFA, current_id: &mut uint) -> ~DFA {
         // we associate a unique number to each state we create to index them
@@ -133,8 +164,8 @@ impl DFA {
         while !unmarked.is_empty() {
             let next_id = unmarked.pop();
 
-            // FIXME: hardcoded value
-            for i in range(0, 255 as u8) {
+            for i in range(0u, 256) {
+                let i = i as u8;
                 let mut trans = ~HashMap::new();
                 let mut newstates = ~HashMap::new();
 
@@ -448,5 +479,360 @@ impl DFA {
 
         ret
     } 
-}
 
+    // finds the rules among `rules` that are not the action of any state of
+    // this automata, and thus can never be matched. `nfa` must be the NFA
+    // this automata was built from, and this must be called before the
+    // automata is minimized. for each such rule, also returns the rule that
+    // wins in the states where it could have matched, if any
+    pub fn shadowed_rules(&self, nfa: &::nfa::NFA, rules: &[uint])
+        -> ~[(uint, Option<uint>)] {
+        let mut ret = ~[];
+
+        for r in rules.iter() {
+            let mut wins = false;
+            let mut shadowed_by = None;
+
+            for (_, st) in self.states.iter() {
+                if st.action == Some(*r) {
+                    wins = true;
+                    break;
+                }
+
+                let act = match st.action {
+                    Some(act) => act,
+                    None => continue
+                };
+
+                for s in st.nfa_states.iter() {
+                    if nfa.find_state(*s).unwrap().action() != Some(*r) {
+                        continue;
+                    }
+
+                    // keep the first rule that shadows this one
+                    shadowed_by = match shadowed_by {
+                        Some(a) if a < act => Some(a),
+                        _ => Some(act)
+                    };
+                }
+            }
+
+            if !wins {
+                ret.push((*r, shadowed_by));
+            }
+        }
+
+        ret
+    }
+
+    // lists the states reachable from the initial state in breadth-first
+    // order, along with the shortest input that leads to each of them. when
+    // several inputs of the same length lead to a state, the smallest one
+    // in byte order is chosen
+    pub fn shortest_inputs(&self) -> ~[(uint, ~[u8])] {
+        let mut ret = ~[(self.initial, ~[])];
+        let mut seen = ~HashSet::new();
+        let mut next = 0u;
+        seen.insert(self.initial);
+
+        while next < ret.len() {
+            let (st, input) = ret[next].clone();
+            let state = self.states.find(&st).unwrap();
+            next += 1;
+
+            for c in range(0u, 256) {
+                match state.trans.find(&(c as u8)) {
+                    Some(dst) if !seen.contains(dst) => {
+                        let mut input = input.clone();
+                        input.push(c as u8);
+                        seen.insert(*dst);
+                        ret.push((*dst, input));
+                    }
+
+                    _ => ()
+                }
+            }
+        }
+
+        ret
+    }
+
+    // finds the pairs of rules that can match the same input, the first one
+    // winning over the second. for each pair, also returns one of the
+    // shortest such inputs. `nfa` must be the NFA this automata was built
+    // from, and this must be called before the automata is minimized
+    pub fn overlapping_rules(&self, nfa: &::nfa::NFA) -> ~[(uint, uint, ~[u8])] {
+        let mut ret: ~[(uint, uint, ~[u8])] = ~[];
+
+        for &(st, ref input) in self.shortest_inputs().iter() {
+            let mut rules = ~[];
+
+            for s in self.states.find(&st).unwrap().nfa_states.iter() {
+                match nfa.find_state(*s).unwrap().action() {
+                    Some(act) if !rules.contains(&act) => rules.push(act),
+                    _ => ()
+                }
+            }
+
+            rules.sort();
+
+            for i in range(0, rules.len()) {
+                for j in range(i + 1, rules.len()) {
+                    let (a, b) = (rules[i], rules[j]);
+
+                    // states are visited by increasing length of their
+                    // input, so the first input found is one of the shortest
+                    if !ret.iter().any(|&(x, y, _)| x == a && y == b) {
+                        ret.push((a, b, input.clone()));
+                    }
+                }
+            }
+        }
+
+        ret.sort();
+        ret
+    }
+
+    // returns, for each action of this automata, its `n` shortest inputs
+    // (in length then byte order) that leave the automata in a state with
+    // this action, i.e. that are tokenized as exactly one lexeme matched by
+    // this action. actions that no input leads to are not in the result
+    pub fn witnesses(&self, n: uint) -> ~HashMap<uint, ~[~[u8]]> {
+        let mut ret: ~HashMap<uint, ~[~[u8]]> = ~HashMap::new();
+        let mut visits = ~HashMap::new();
+        let mut queue = ~[(self.initial, ~[])];
+        let mut next = 0u;
+
+        if n == 0 {
+            return ret;
+        }
+
+        // since inputs are explored in length then byte order, the first n
+        // inputs that reach a state are its n smallest ones, and any input
+        // that goes through this state will be greater than the inputs that
+        // follow the same path from one of them. thus we don't need to visit
+        // a state more than n times
+        visits.insert(self.initial, 1u);
+
+        while next < queue.len() {
+            let (st, input) = queue[next].clone();
+            let state = self.states.find(&st).unwrap();
+            next += 1;
+
+            match state.action {
+                Some(act) => {
+                    let inputs = ret.find_or_insert(act, ~[]);
+                    if inputs.len() < n {
+                        inputs.push(input.clone());
+                    }
+                }
+
+                None => ()
+            }
+
+            for c in range(0u, 256) {
+                let dst = match state.trans.find(&(c as u8)) {
+                    Some(dst) => *dst,
+                    None => continue
+                };
+
+                let count = visits.find_or_insert(dst, 0);
+                if *count < n {
+                    *count += 1;
+
+                    let mut input = input.clone();
+                    input.push(c as u8);
+                    queue.push((dst, input));
+                }
+            }
+        }
+
+        ret
+    }
+
+    // finds the inputs no rule matches a prefix of, on which the default
+    // action of the lexer applies. returns a list of (prefix, bytes, eof)
+    // meaning that no rule matches any input that starts with `prefix`
+    // followed by one of the ranges of `bytes`, nor `prefix` itself at
+    // the end of the input if `eof` is true. only one of the shortest
+    // prefixes leading to each state is given
+    pub fn uncovered_inputs(&self) -> ~[(~[u8], ~[(u8, u8)], bool)] {
+        let mut ret = ~[];
+        let mut queue = ~[(self.initial, ~[])];
+        let mut seen = ~HashSet::new();
+        let mut next = 0u;
+        seen.insert(self.initial);
+
+        // only the paths through non-accepting states are explored since
+        // once a rule matched, the default action can't apply anymore
+        while next < queue.len() {
+            let (st, input) = queue[next].clone();
+            let state = self.states.find(&st).unwrap();
+            next += 1;
+
+            for c in range(0u, 256) {
+                match state.trans.find(&(c as u8)) {
+                    Some(dst) if !seen.contains(dst) && !self.finals.contains(dst) => {
+                        let mut input = input.clone();
+                        input.push(c as u8);
+                        seen.insert(*dst);
+                        queue.push((*dst, input));
+                    }
+
+                    _ => ()
+                }
+            }
+
+            let ranges = state.dead_ranges();
+            let eof = st != self.initial;
+            if !ranges.is_empty() || eof {
+                ret.push((input, ranges, eof));
+            }
+        }
+
+        ret
+    }
+
+    // finds the states in which the scanner may have to back up, i.e. the
+    // non-accepting states that can be reached after going through an
+    // accepting state. if the scanner stops in such a state, because the
+    // input ends or the next byte is one of its dead ranges, it has to go
+    // back to the end of the last match. returns, for each of them, one of
+    // the shortest inputs leading to it through an accepting state, its
+    // dead ranges and, if the state is on a cycle of non-accepting states
+    // that makes the scanning time quadratic, an input that loops on it.
+    // repeating this input makes the scanner read arbitrarily far before
+    // backing up, and the scan that resumes after the match reads as far
+    // again (see `rescans`)
+    pub fn backing_up_states(&self) -> ~[(uint, ~[u8], ~[(u8, u8)], Option<~[u8]>)] {
+        let mut ret = ~[];
+
+        // explore pairs of states and whether an accepting state was
+        // already reached on the way
+        let mut queue = ~[((self.initial, false), ~[])];
+        let mut seen = ~HashSet::new();
+        let mut next = 0u;
+        seen.insert((self.initial, false));
+
+        while next < queue.len() {
+            let ((st, matched), input) = queue[next].clone();
+            let state = self.states.find(&st).unwrap();
+            next += 1;
+
+            if matched && !state.is_final() {
+                let lp = match self.cycle(st) {
+                    Some(lp) => {
+                        if self.rescans(input.as_slice(), lp.as_slice()) { Some(lp) } else { None }
+                    }
+                    None => None
+                };
+
+                ret.push((st, input.clone(), state.dead_ranges(), lp));
+            }
+
+            for c in range(0u, 256) {
+                let dst = match state.trans.find(&(c as u8)) {
+                    Some(dst) => *dst,
+                    None => continue
+                };
+
+                let matched = matched || self.finals.contains(&dst);
+                if !seen.contains(&(dst, matched)) {
+                    let mut input = input.clone();
+                    input.push(c as u8);
+                    seen.insert((dst, matched));
+                    queue.push(((dst, matched), input));
+                }
+            }
+        }
+
+        ret
+    }
+
+    // whether, once the scanner backed up to the end of the last match on
+    // `input` followed by repetitions of `lp`, the scan that resumes there
+    // also reads all of the repetitions without matching them. this is
+    // what makes the scanning time quadratic: with the rules `a` and `a*b`
+    // each "a" of "aaa..." is followed by a scan to the end of the input,
+    // while with `a` and `ab*c` the scan of "bbb..." stops at the first "b"
+    fn rescans(&self, input: &[u8], lp: &[u8]) -> bool {
+        // the last match ends at the last accepting state reached
+        let mut st = self.initial;
+        let mut match_end = 0;
+
+        for (i, c) in input.iter().enumerate() {
+            st = self.transition(st, *c).unwrap();
+
+            if self.finals.contains(&st) {
+                match_end = i + 1;
+            }
+        }
+
+        let mut st = self.initial;
+        for c in input.slice_from(match_end).iter() {
+            match self.transition(st, *c) {
+                Some(dst) => st = dst,
+                None => return false
+            }
+        }
+
+        // follow the repetitions until the state reached after one of them
+        // repeats, remembering if an accepting state is reached during each
+        let mut states = ~[st];
+        let mut accepting = ~[];
+
+        loop {
+            let mut acc = false;
+
+            for c in lp.iter() {
+                match self.transition(st, *c) {
+                    Some(dst) => st = dst,
+                    None => return false
+                }
+
+                acc = acc || self.finals.contains(&st);
+            }
+
+            accepting.push(acc);
+
+            match states.position_elem(&st) {
+                Some(i) => return !accepting.slice_from(i).iter().any(|a| *a),
+                None => states.push(st)
+            }
+        }
+    }
+
+    // one of the shortest inputs that go from `st` back to `st` only through
+    // non-accepting states, if any
+    fn cycle(&self, st: uint) -> Option<~[u8]> {
+        let mut queue = ~[(st, ~[])];
+        let mut seen = ~HashSet::new();
+        let mut next = 0u;
+
+        while next < queue.len() {
+            let (s, input) = queue[next].clone();
+            next += 1;
+
+            for c in range(0u, 256) {
+                let dst = match self.transition(s, c as u8) {
+                    Some(dst) => dst,
+                    None => continue
+                };
+
+                let mut input = input.clone();
+                input.push(c as u8);
+
+                if dst == st {
+                    return Some(input);
+                }
+
+                if !seen.contains(&dst) && !self.finals.contains(&dst) {
+                    seen.insert(dst);
+                    queue.push((dst, input));
+                }
+            }
+        }
+
+        None
+    }
+}
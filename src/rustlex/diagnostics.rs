@@ -0,0 +1,78 @@
+use std::io::Writer;
+
+/*
+ * Diagnostics emitted while compiling a lexer. Instead of printing them
+ * directly, the compiler reports them to a sink implementing the
+ * `Diagnostics` trait, so that users can filter, collect or format them
+ * as they wish.
+ */
+
+#[deriving(Eq, Ord, Clone)]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error
+}
+
+impl Level {
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            Debug => "debug",
+            Info => "info",
+            Warning => "warning",
+            Error => "error"
+        }
+    }
+}
+
+// a message about the rule `rule` or the condition `condition` if they are
+// given, or about the whole lexer otherwise
+#[deriving(Eq, Clone)]
+pub struct Diagnostic {
+    level: Level,
+    message: ~str,
+    rule: Option<uint>,
+    condition: Option<~str>
+}
+
+pub trait Diagnostics {
+    fn report(&mut self, diag: Diagnostic);
+}
+
+// prints the diagnostics of at least a given level to stderr
+pub struct StderrDiagnostics {
+    min_level: Level
+}
+
+impl StderrDiagnostics {
+    pub fn new(min_level: Level) -> StderrDiagnostics {
+        StderrDiagnostics { min_level: min_level }
+    }
+}
+
+impl Diagnostics for StderrDiagnostics {
+    fn report(&mut self, diag: Diagnostic) {
+        if diag.level >= self.min_level {
+            let err = &mut ::std::io::stdio::stderr() as &mut Writer;
+            writeln!(err, "{:s}: {:s}", diag.level.to_str(), diag.message);
+        }
+    }
+}
+
+// keeps all the diagnostics it receives
+pub struct CollectedDiagnostics {
+    diagnostics: ~[Diagnostic]
+}
+
+impl CollectedDiagnostics {
+    pub fn new() -> CollectedDiagnostics {
+        CollectedDiagnostics { diagnostics: ~[] }
+    }
+}
+
+impl Diagnostics for CollectedDiagnostics {
+    fn report(&mut self, diag: Diagnostic) {
+        self.diagnostics.push(diag);
+    }
+}
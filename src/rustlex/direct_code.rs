@@ -0,0 +1,105 @@
+use std::io::Writer;
+
+/*
+ * Direct-coded backend: instead of emitting the transition and accepting
+ * tables as static arrays, emit them as functions where each state of the
+ * automata becomes an arm of a `match` on the current state, itself
+ * containing a `match` on the input byte. Consecutive bytes leading to the
+ * same state are folded into range patterns, and transitions to the dead
+ * state (0) are left to the wildcard arm. This lets rustc compile the
+ * automata into jump tables and comparisons, which is usually faster than
+ * table lookups for small lexers.
+ * Both functions have the same signature and semantics as the ones emitted
+ * along with the tables, so the driver doesn't need to know which backend
+ * was used.
+ */
+
+// folds a row of the transition table into a list of (first, last, dst)
+// ranges of consecutive bytes that lead to the same non-dead state
+fn fold_ranges(row: &[uint]) -> ~[(uint, uint, uint)] {
+    let mut ranges = ~[];
+    let mut c = 0u;
+
+    while c < row.len() {
+        let dst = row[c];
+        let first = c;
+
+        while c + 1 < row.len() && row[c + 1] == dst {
+            c += 1;
+        }
+
+        if dst != 0 {
+            ranges.push((first, c, dst));
+        }
+
+        c += 1;
+    }
+
+    ranges
+}
+
+// number of ranges in the code emitted by `print_transition_code`
+pub fn count_ranges(table: &[~[uint]]) -> uint {
+    table.iter().skip(1).fold(0, |n, row| n + fold_ranges(row.as_slice()).len())
+}
+
+pub fn print_transition_code(table: &[~[uint]], out: &mut Writer) {
+    writeln!(out, "fn transition(st: usize, i: u8) -> usize \\{");
+
+    // state 0 is the dead state and has no transitions
+    if table.iter().skip(1).all(|row| row.iter().all(|dst| *dst == 0)) {
+        // the input byte is only used by the transitions
+        writeln!(out, "    let _ = i;");
+    }
+
+    writeln!(out, "    match st \\{");
+
+    let mut st = 1u;
+    while st < table.len() {
+        let ranges = fold_ranges(table[st]);
+
+        if ranges.is_empty() {
+            st += 1;
+            continue
+        }
+
+        writeln!(out, "        {:u} => match i \\{", st);
+
+        for &(first, last, dst) in ranges.iter() {
+            if first == last {
+                writeln!(out, "            {:u}u8 => {:u},", first, dst);
+            } else {
+                writeln!(out, "            {:u}u8..={:u}u8 => {:u},",
+                    first, last, dst);
+            }
+        }
+
+        // rustc warns about a wildcard arm that can't be reached
+        let covered = ranges.iter().fold(0, |n, &(first, last, _)| n + last - first + 1);
+        if covered < 256 {
+            writeln!(out, "            _ => 0");
+        }
+
+        writeln!(out, "        \\},");
+        st += 1;
+    }
+
+    writeln!(out, "        _ => 0");
+    writeln!(out, "    \\}");
+    writeln!(out, "\\}");
+}
+
+pub fn print_accepting_code(finals: &[uint], out: &mut Writer) {
+    writeln!(out, "fn accepting(st: usize) -> usize \\{");
+    writeln!(out, "    match st \\{");
+
+    for (st, act) in finals.iter().enumerate() {
+        if *act != 0 {
+            writeln!(out, "        {:u} => {:u},", st, *act);
+        }
+    }
+
+    writeln!(out, "        _ => 0");
+    writeln!(out, "    \\}");
+    writeln!(out, "\\}");
+}
@@ -0,0 +1,111 @@
+use automata::Automata;
+use automata::AutomataState;
+use dfa::DFA;
+use std::hashmap::HashMap;
+use std::hashmap::HashSet;
+
+/*
+ * Comparison of the languages of two automata. The product of both automata
+ * is explored breadth-first, which gives for each pair of states the
+ * shortest input that leads to it. When we find a pair of states whose
+ * actions are considered different, the input that led to it is a shortest
+ * distinguishing input. A missing automata is considered to have only a
+ * dead state. The state 0 is used as the dead state of both automata, since
+ * no actual state has this number.
+ */
+
+fn action(dfa: Option<&DFA>, st: uint) -> Option<uint> {
+    match dfa {
+        Some(dfa) if st != 0 => dfa.find_state(st).unwrap().action(),
+        _ => None
+    }
+}
+
+fn transition(dfa: Option<&DFA>, st: uint, c: u8) -> uint {
+    match dfa {
+        Some(dfa) if st != 0 => match dfa.transition(st, c) {
+            Some(dst) => dst,
+            None => 0
+        },
+        _ => 0
+    }
+}
+
+// explores the product of `a` and `b`, calling `f` with the shortest input
+// leading to each pair of states and the actions of these states, until it
+// returns true
+fn explore(a: Option<&DFA>, b: Option<&DFA>,
+           f: |&[u8], Option<uint>, Option<uint>| -> bool) {
+    let init = (a.map_default(0, |d| d.initial()), b.map_default(0, |d| d.initial()));
+    let mut queue = ~[(init, ~[])];
+    let mut seen = ~HashSet::new();
+    let mut next = 0u;
+    seen.insert(init);
+
+    while next < queue.len() {
+        let ((sa, sb), input) = queue[next].clone();
+        next += 1;
+
+        if f(input, action(a, sa), action(b, sb)) {
+            return;
+        }
+
+        for c in range(0u, 256) {
+            let dst = (transition(a, sa, c as u8), transition(b, sb, c as u8));
+
+            if dst == (0, 0) || seen.contains(&dst) {
+                continue;
+            }
+
+            let mut input = input.clone();
+            input.push(c as u8);
+            seen.insert(dst);
+            queue.push((dst, input));
+        }
+    }
+}
+
+// finds one of the shortest inputs on which `differ` holds for the actions
+// of the states `a` and `b` end in. returns this input and both actions
+pub fn find_difference(a: Option<&DFA>, b: Option<&DFA>,
+                       differ: |Option<uint>, Option<uint>| -> bool)
+    -> Option<(~[u8], Option<uint>, Option<uint>)> {
+    let mut ret = None;
+
+    explore(a, b, |input, act_a, act_b| {
+        if differ(act_a, act_b) {
+            ret = Some((input.to_owned(), act_a, act_b));
+            true
+        } else {
+            false
+        }
+    });
+
+    ret
+}
+
+// finds all the pairs of actions of `a` and `b` for which `differ` holds
+// and that some input leads to. for each pair, returns up to `n` of the
+// shortest such inputs. the result is sorted by pair of actions
+pub fn find_differences(a: Option<&DFA>, b: Option<&DFA>, n: uint,
+                        differ: |Option<uint>, Option<uint>| -> bool)
+    -> ~[(Option<uint>, Option<uint>, ~[~[u8]])] {
+    let mut found: HashMap<(Option<uint>, Option<uint>), ~[~[u8]]> = HashMap::new();
+
+    explore(a, b, |input, act_a, act_b| {
+        if differ(act_a, act_b) {
+            let inputs = found.find_or_insert((act_a, act_b), ~[]);
+            if inputs.len() < n {
+                inputs.push(input.to_owned());
+            }
+        }
+
+        false
+    });
+
+    let mut ret: ~[(Option<uint>, Option<uint>, ~[~[u8]])] = found.move_iter()
+        .map(|((l, r), inputs)| (l, r, inputs))
+        .collect();
+    ret.sort();
+    ret
+}
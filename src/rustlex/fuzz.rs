@@ -0,0 +1,151 @@
+use automata::Automata;
+use automata::AutomataState;
+use dfa::DFA;
+use std::hashmap::HashMap;
+use std::hashmap::HashSet;
+use std::rand::Rng;
+
+/*
+ * Generates random inputs for a lexer by walking randomly through the
+ * automata of one of its conditions. The walk favours the transitions
+ * that were taken the least so far, so that generating enough inputs
+ * eventually covers all the states and transitions of the automata.
+ * Random "noise" bytes can be inserted between lexemes to also exercise
+ * the default action and error paths of the lexer under test.
+ */
+
+// probability to stop a lexeme when in an accepting state
+static STOP_PROBABILITY: f64 = 0.3;
+
+// a lexeme is cut after this many bytes, even if it isn't complete
+static MAX_LEXEME_LEN: uint = 64;
+
+pub struct Fuzzer<'a> {
+    priv dfa: &'a DFA,
+
+    // the transitions of each state, sorted by byte
+    priv trans: ~HashMap<uint, ~[(u8, uint)]>,
+
+    // number of times each transition was taken
+    priv visits: ~HashMap<(uint, u8), uint>,
+
+    // probability to insert a random byte before each lexeme
+    priv noise: f64
+}
+
+impl<'a> Fuzzer<'a> {
+    pub fn new(dfa: &'a DFA) -> Fuzzer<'a> {
+        let mut trans = ~HashMap::new();
+
+        for (i, st) in dfa.states_iter() {
+            let mut t = st.transitions();
+            t.sort();
+            trans.insert(*i, t);
+        }
+
+        Fuzzer {
+            dfa: dfa,
+            trans: trans,
+            visits: ~HashMap::new(),
+            noise: 0.0
+        }
+    }
+
+    // sets the probability to insert a random byte before each lexeme
+    pub fn set_noise(&mut self, noise: f64) {
+        self.noise = noise;
+    }
+
+    // returns the number of transitions taken at least once so far, and the
+    // total number of transitions of the automata
+    pub fn coverage(&self) -> (uint, uint) {
+        let total = self.trans.iter().fold(0, |n, (_, t)| n + t.len());
+        (self.visits.len(), total)
+    }
+
+    // returns the states that were never reached so far
+    pub fn unvisited_states(&self) -> ~[uint] {
+        let mut visited = ~HashSet::new();
+        visited.insert(self.dfa.initial());
+
+        for (&(st, c), _) in self.visits.iter() {
+            for &(ch, dst) in self.trans.find(&st).unwrap().iter() {
+                if ch == c {
+                    visited.insert(dst);
+                }
+            }
+        }
+
+        let mut ret: ~[uint] = self.trans.iter()
+            .map(|(i, _)| *i)
+            .filter(|i| !visited.contains(i))
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    // appends a random lexeme to `out`
+    fn gen_lexeme<R: Rng>(&mut self, rng: &mut R, out: &mut ~[u8]) {
+        let mut st = self.dfa.initial();
+        let mut len = 0u;
+
+        loop {
+            let trans = self.trans.find(&st).unwrap();
+            let accepting = self.dfa.find_state(st).unwrap().is_final();
+
+            if trans.is_empty() || len >= MAX_LEXEME_LEN {
+                break;
+            }
+
+            if accepting && rng.gen::<f64>() < STOP_PROBABILITY {
+                break;
+            }
+
+            // the less a transition was taken, the more likely it is to be
+            // chosen
+            let weights: ~[f64] = trans.iter().map(|&(c, _)| {
+                let n = match self.visits.find(&(st, c)) {
+                    Some(n) => *n,
+                    None => 0
+                };
+
+                1.0 / (1.0 + n as f64)
+            }).collect();
+
+            let total = weights.iter().fold(0.0, |a, w| a + *w);
+            let mut pick = rng.gen::<f64>() * total;
+            let mut chosen = trans.len() - 1;
+
+            for (i, w) in weights.iter().enumerate() {
+                if pick < *w {
+                    chosen = i;
+                    break;
+                }
+
+                pick -= *w;
+            }
+
+            let (c, dst) = trans[chosen];
+            *self.visits.find_or_insert((st, c), 0) += 1;
+
+            out.push(c);
+            st = dst;
+            len += 1;
+        }
+    }
+
+    // generates an input made of `lexemes` random lexemes
+    pub fn gen_input<R: Rng>(&mut self, rng: &mut R, lexemes: uint) -> ~[u8] {
+        let mut ret = ~[];
+
+        for _ in range(0, lexemes) {
+            if self.noise > 0.0 && rng.gen::<f64>() < self.noise {
+                ret.push(rng.gen::<u8>());
+            }
+
+            self.gen_lexeme(rng, &mut ret);
+        }
+
+        ret
+    }
+}
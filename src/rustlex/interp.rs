@@ -0,0 +1,117 @@
+use std::hashmap::HashMap;
+use std::io::Reader;
+
+/*
+ * An interpreter for the automata of a lexer. It runs the same transition
+ * and accepting tables as the ones `Lexer::write` emits, with the same
+ * semantics as the generated driver, but directly in-process. This allows
+ * to try out a set of rules without having to generate, compile and run
+ * a lexer.
+ */
+
+// a lexeme recognized by the interpreter. `rule` is the number of the rule
+// that matched it, in the order the rules were given to the lexer starting
+// from 1, or 0 if no rule matched and the default action applies. `start`
+// and `end` are the byte offsets of the lexeme in the input
+#[deriving(Eq, Clone)]
+pub struct Token {
+    rule: uint,
+    start: uint,
+    end: uint
+}
+
+pub struct Interpreter {
+    priv trans: ~[~[uint]],
+    priv accepting: ~[uint],
+    priv conditions: ~HashMap<~str, uint>,
+    priv condition: uint
+}
+
+impl Interpreter {
+    // `conditions` maps the name of each condition to its initial state
+    // in the tables. the interpreter starts in the `Initial` condition
+    pub fn new(trans: ~[~[uint]], accepting: ~[uint],
+               conditions: ~HashMap<~str, uint>) -> Interpreter {
+        let initial = match conditions.find_equiv(&"Initial") {
+            Some(st) => *st,
+            None => 0
+        };
+
+        Interpreter {
+            trans: trans,
+            accepting: accepting,
+            conditions: conditions,
+            condition: initial
+        }
+    }
+
+    // switches to the condition `cond`. returns false if there is no such
+    // condition, in which case the current condition is left unchanged
+    pub fn set_condition(&mut self, cond: &str) -> bool {
+        match self.conditions.find_equiv(&cond) {
+            Some(st) => { self.condition = *st; true }
+            None => false
+        }
+    }
+
+    // finds the longest lexeme starting at `pos` in `input` in the current
+    // condition. returns the rule that matched and the end of the lexeme
+    pub fn longest_match(&self, input: &[u8], pos: uint) -> Option<(uint, uint)> {
+        let mut current_st = self.condition;
+        let mut last_match = None;
+        let mut i = pos;
+
+        while current_st != 0 && i < input.len() {
+            current_st = self.trans[current_st][input[i] as uint];
+            i += 1;
+
+            let action = self.accepting[current_st];
+            if action != 0 {
+                last_match = Some((action, i));
+            }
+        }
+
+        last_match
+    }
+
+    // returns the next token of `input` starting at `pos`. if no rule
+    // matches, the default action consumes a single byte
+    pub fn next_token(&self, input: &[u8], pos: uint) -> Option<Token> {
+        if pos >= input.len() {
+            return None;
+        }
+
+        match self.longest_match(input, pos) {
+            Some((rule, end)) => Some(Token { rule: rule, start: pos, end: end }),
+            None => Some(Token { rule: 0, start: pos, end: pos + 1 })
+        }
+    }
+
+    // tokenizes the whole input, calling `f` on each token and its lexeme.
+    // `f` may switch the interpreter to another condition, which then
+    // applies from the next token on
+    pub fn run(&mut self, input: &[u8], f: |&mut Interpreter, &Token, &[u8]|) {
+        let mut pos = 0u;
+
+        loop {
+            let tok = match self.next_token(input, pos) {
+                Some(t) => t,
+                None => break
+            };
+
+            pos = tok.end;
+            f(self, &tok, input.slice(tok.start, tok.end));
+        }
+    }
+
+    pub fn tokenize(&mut self, input: &[u8]) -> ~[Token] {
+        let mut ret = ~[];
+        self.run(input, |_, tok, _| ret.push(tok.clone()));
+        ret
+    }
+
+    pub fn tokenize_reader(&mut self, input: &mut Reader) -> ~[Token] {
+        let buf = input.read_to_end();
+        self.tokenize(buf)
+    }
+}
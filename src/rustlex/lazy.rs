@@ -0,0 +1,212 @@
+use nfa::NFA;
+use std::hashmap::HashMap;
+use std::hashmap::HashSet;
+
+/*
+ * A lazy DFA matcher. Instead of running the whole subset construction
+ * ahead of time like DFA::new_from_nfa does, this keeps the NFA and only
+ * builds the DFA states that are actually reached while scanning the input.
+ * Built states are kept in a cache of bounded size. When the cache is full
+ * it is flushed and the construction starts over from the current state.
+ * If the cache has to be flushed too often while looking for a single
+ * match, the DFA states are obviously not reused enough for the cache to
+ * be worth it, and we fall back to a simulation of the NFA for the rest
+ * of the match.
+ */
+
+// number of cache flushes allowed during a single match before falling
+// back to NFA simulation
+static MAX_FLUSHES: uint = 3;
+
+// marks a transition that has not been computed yet
+static UNKNOWN: uint = ::std::uint::max_value;
+
+// marks a transition to the dead state
+static DEAD: uint = ::std::uint::max_value - 1;
+
+struct State {
+    // the set of NFA states this state corresponds to, sorted
+    priv nfa_states: ~[uint],
+
+    // the destination of each transition, UNKNOWN or DEAD
+    priv trans: ~[uint],
+
+    priv action: Option<uint>
+}
+
+pub struct LazyDFA<'a> {
+    priv nfa: &'a NFA,
+    priv states: ~[State],
+
+    // finds the DFA state corresponding to a set of NFA states
+    priv index: ~HashMap<~[uint], uint>,
+
+    // maximal number of states kept in the cache
+    priv capacity: uint,
+
+    // number of flushes since the beginning of the current match
+    priv flushes: uint,
+
+    // number of matches finished by NFA simulation
+    priv fallbacks: uint
+}
+
+fn sorted_states(st: &HashSet<uint>) -> ~[uint] {
+    let mut v: ~[uint] = st.iter().map(|s| *s).collect();
+    v.sort();
+    v
+}
+
+impl<'a> LazyDFA<'a> {
+    pub fn new(nfa: &'a NFA, capacity: uint) -> LazyDFA<'a> {
+        // we need room for at least the current state and its successor
+        let capacity = if capacity < 2 { 2 } else { capacity };
+
+        LazyDFA {
+            nfa: nfa,
+            states: ~[],
+            index: ~HashMap::new(),
+            capacity: capacity,
+            flushes: 0,
+            fallbacks: 0
+        }
+    }
+
+    // number of DFA states currently in the cache
+    pub fn cached_states(&self) -> uint {
+        self.states.len()
+    }
+
+    // number of matches for which the cache thrashed and the matcher fell
+    // back to NFA simulation
+    pub fn fallbacks(&self) -> uint {
+        self.fallbacks
+    }
+
+    fn flush(&mut self) {
+        self.states.clear();
+        self.index.clear();
+        self.flushes += 1;
+    }
+
+    // returns the number of the cached state that corresponds to the set
+    // of NFA states `st`, creating it if needed. the caller must make sure
+    // the cache is not full
+    fn add_state(&mut self, st: &HashSet<uint>) -> uint {
+        let key = sorted_states(st);
+
+        match self.index.find(&key) {
+            Some(i) => return *i,
+            None => ()
+        }
+
+        let id = self.states.len();
+        self.states.push(State {
+            nfa_states: key.clone(),
+            trans: ::std::vec::from_elem(256, UNKNOWN),
+            action: self.nfa.action(st)
+        });
+
+        self.index.insert(key, id);
+        id
+    }
+
+    fn nfa_set(&self, st: uint) -> ~HashSet<uint> {
+        let mut set = ~HashSet::new();
+
+        for s in self.states[st].nfa_states.iter() {
+            set.insert(*s);
+        }
+
+        set
+    }
+
+    // computes the transition from the cached state `st` on `c`. returns
+    // the new state, DEAD, or None if the cache thrashes and the caller
+    // should switch to NFA simulation from the set of NFA states `st`
+    fn transition(&mut self, st: uint, c: u8) -> Option<uint> {
+        let dst = self.states[st].trans[c as uint];
+        if dst != UNKNOWN {
+            return Some(dst);
+        }
+
+        let current = self.nfa_set(st);
+        let next = self.nfa.step(current, c);
+
+        if next.is_empty() {
+            self.states[st].trans[c as uint] = DEAD;
+            return Some(DEAD);
+        }
+
+        // no need to make room for a state that is already cached
+        match self.index.find(&sorted_states(next)) {
+            Some(&dst) => {
+                self.states[st].trans[c as uint] = dst;
+                return Some(dst);
+            }
+
+            None => ()
+        }
+
+        let mut st = st;
+        if self.states.len() >= self.capacity {
+            if self.flushes >= MAX_FLUSHES {
+                return None;
+            }
+
+            self.flush();
+            st = self.add_state(current);
+        }
+
+        let dst = self.add_state(next);
+        self.states[st].trans[c as uint] = dst;
+        Some(dst)
+    }
+
+    // finds the longest prefix of `input` that matches one of the rules of
+    // the NFA. returns the action of the winning rule and the length of the
+    // match, or None if no rule matches a non-empty prefix
+    pub fn longest_match(&mut self, input: &[u8]) -> Option<(uint, uint)> {
+        if self.states.len() >= self.capacity {
+            self.flush();
+        }
+
+        self.flushes = 0;
+
+        let init = self.nfa.initial_states();
+        let mut current = self.add_state(init);
+        let mut last_match = None;
+        let mut pos = 0u;
+
+        while pos < input.len() {
+            match self.transition(current, input[pos]) {
+                Some(DEAD) => break,
+                Some(dst) => {
+                    pos += 1;
+                    current = dst;
+
+                    match self.states[dst].action {
+                        Some(act) => last_match = Some((act, pos)),
+                        None => ()
+                    }
+                }
+
+                None => {
+                    // the cache thrashes, finish this match by simulating
+                    // the NFA directly
+                    self.fallbacks += 1;
+                    let set = self.nfa_set(current);
+
+                    match self.nfa.longest_match_from(set, input, pos) {
+                        Some(m) => last_match = Some(m),
+                        None => ()
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        last_match
+    }
+}
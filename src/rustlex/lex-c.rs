@@ -33,5 +33,8 @@ fn main() {
     let lex = ~Lexer::new(regexps);
     let out = &mut io::stdio::stdout() as &mut io::Writer;
 
-    lex.write(None, out);
+    match lex.write(None, out) {
+        Ok(()) => (),
+        Err(e) => fail!("{:s}", e)
+    }
 }
@@ -0,0 +1,56 @@
+extern mod rustlex;
+
+use rustlex::Lexer;
+
+// compares two versions of a lexer specification file (see spec.rs for the
+// format) and reports, rule by rule, which inputs newly match, no longer
+// match or are matched by another rule
+fn main() {
+    use rustlex::spec::parse_spec;
+    use std::io;
+    use std::io::File;
+    use std::os;
+
+    let args = os::args();
+    let err = &mut io::stdio::stderr() as &mut io::Writer;
+
+    if args.len() != 3 {
+        writeln!(err, "usage: {:s} OLD_SPEC NEW_SPEC", args[0]);
+        os::set_exit_status(1);
+        return;
+    }
+
+    let mut lexers = ~[];
+
+    for path in args.slice_from(1).iter() {
+        let contents = match File::open(&Path::new(path.as_slice())) {
+            Some(mut f) => f.read_to_end(),
+            None => {
+                writeln!(err, "Unable to open {:s}", *path);
+                os::set_exit_status(1);
+                return;
+            }
+        };
+
+        let contents = match ::std::str::from_utf8_opt(contents) {
+            Some(s) => s,
+            None => {
+                writeln!(err, "{:s}: not valid UTF-8", *path);
+                os::set_exit_status(1);
+                return;
+            }
+        };
+
+        match parse_spec(contents) {
+            Ok(spec) => lexers.push(Lexer::from_spec(spec)),
+            Err(e) => {
+                writeln!(err, "{:s}: {:s}", *path, e);
+                os::set_exit_status(1);
+                return;
+            }
+        }
+    }
+
+    let out = &mut io::stdio::stdout() as &mut io::Writer;
+    lexers[0].write_diff_report(&lexers[1], 3, out);
+}
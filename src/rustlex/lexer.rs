@@ -1,21 +1,299 @@
 use action::Action;
 use automata::Automata;
+use diagnostics::Debug;
+use diagnostics::Diagnostic;
+use diagnostics::Diagnostics;
+use diagnostics::Error;
+use diagnostics::StderrDiagnostics;
+use diagnostics::Warning;
+use direct_code;
+use extra::time::precise_time_ns;
+use equiv::find_difference;
+use equiv::find_differences;
+use fuzz::Fuzzer;
+use interp::Interpreter;
+use lazy::LazyDFA;
+use nfa::NFA;
+use spec::Spec;
+use stats::ConditionStats;
+use stats::Stats;
+use stats::TableStats;
+use stats::Timings;
+use stats;
 use std::hashmap::HashMap;
 use std::io::Writer;
+use std::io::mem::MemWriter;
+use template::AcceptingTable;
+use template::ActionType;
+use template::ConditionEnum;
+use template::Conditions;
+use template::Epilogue;
+use template::Hole;
+use template::LexerType;
+use template::Prologue;
+use template::StateActions;
+use template::StateType;
+use template::Support;
+use template::Text;
+use template::TokenType;
+use template::Trace;
+use template::TransitionTable;
+use template::parse_template;
 use trans_table::transition_table;
 
+// the drivers used by `write` when no template file is given, for each
+// kind of input
+static READER_TEMPLATE: &'static str = include_str!("simul.rst");
+static SLICE_TEMPLATE: &'static str = include_str!("simul_slice.rst");
+static PUSH_TEMPLATE: &'static str = include_str!("simul_push.rst");
+
+// types and functions shared by the drivers, see template.rs
+static SUPPORT_CODE: &'static str = include_str!("support.rst");
+
+// reads the contents of the template file `fname`
+fn load_template(fname: &str) -> Result<~[u8], ~str> {
+    use std::io::File;
+
+    match File::open(&Path::new(fname)) {
+        Some(mut f) => Ok(f.read_to_end()),
+        None => Err(format!("unable to open template file {:s}", fname))
+    }
+}
+
+// integer type used for the entries of the tables emitted by `write`
+#[deriving(Eq, Clone)]
+pub enum TableType {
+    // use the smallest type that can hold all the entries of each table
+    AutoType,
+    U8Type,
+    U16Type,
+    U32Type,
+    UintType
+}
+
+impl TableType {
+    // returns the name of the Rust type to use for a table whose greatest
+    // entry is `max`. returns an error if the type was forced and is too
+    // small
+    fn type_name(&self, max: uint) -> Result<&'static str, ~str> {
+        match *self {
+            AutoType if max <= 0xff => Ok("u8"),
+            AutoType if max <= 0xffff => Ok("u16"),
+            AutoType if max <= 0xffffffff => Ok("u32"),
+            AutoType => Ok("usize"),
+            U8Type if max <= 0xff => Ok("u8"),
+            U16Type if max <= 0xffff => Ok("u16"),
+            U32Type if max <= 0xffffffff => Ok("u32"),
+            UintType => Ok("usize"),
+            _ => Err(format!("table entry {:u} does not fit in the forced table type", max))
+        }
+    }
+}
+
+// kind of code emitted by `write` to represent the automata
+#[deriving(Eq, Clone)]
+pub enum Backend {
+    // static transition and accepting tables
+    TableDriven,
+    // a `match` on the input byte for each state
+    DirectCoded
+}
+
+// kind of input the lexer emitted by `write` with the default template
+// reads
+#[deriving(Eq, Clone)]
+pub enum Input {
+    // any std::io::Read, through a buffer
+    ReaderInput,
+    // a slice of bytes or a string in memory, without copying it
+    SliceInput,
+    // chunks of input given to the lexer as they come
+    PushInput
+}
+
+// options controlling the checks done when building a lexer
+pub struct Options {
+    // report rules that can never be matched as errors instead of warnings
+    shadowed_rules_are_errors: bool,
+
+    // report rules that match the empty string as errors instead of warnings
+    empty_rules_are_errors: bool,
+
+    // make it an error for a condition to have inputs on which the default
+    // action applies
+    require_full_coverage: bool,
+
+    // warn about inputs that force the generated lexer to rescan the same
+    // input over and over (see `write_backing_up_report`)
+    warn_quadratic_rescanning: bool
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            shadowed_rules_are_errors: false,
+            empty_rules_are_errors: false,
+            require_full_coverage: false,
+            warn_quadratic_rescanning: false
+        }
+    }
+}
+
+// a pair of rules of the same condition that can match the same input. the
+// `first` rule always wins over the `second` one on `example`, which is one
+// of the shortest inputs they both match
+#[deriving(Eq, Clone)]
+pub struct Ambiguity {
+    condition: ~str,
+    first: uint,
+    second: uint,
+    example: ~[u8]
+}
+
+// an input on which two lexers disagree. in the condition `condition`, the
+// whole `input` is tokenized as a single lexeme of the rule `left` by the
+// first lexer and of the rule `right` by the second one, None meaning that
+// it isn't tokenized as a single lexeme
+#[deriving(Eq, Clone)]
+pub struct Difference {
+    condition: ~str,
+    input: ~[u8],
+    left: Option<uint>,
+    right: Option<uint>
+}
+
+// formats an input for use in reports, escaping non-printable characters
+fn escape(input: &[u8]) -> ~str {
+    let mut ret = ~"";
+
+    for c in input.iter() {
+        let c = *c as char;
+        if c == '"' || c == '\\' {
+            ret.push_char('\\');
+            ret.push_char(c);
+        } else if c >= ' ' && c <= '~' {
+            ret.push_char(c);
+        } else {
+            ret.push_str(format!("\\x{:02x}", c as uint));
+        }
+    }
+
+    ret
+}
+
+// an input on which the default action applies since no rule of the
+// condition `condition` matches it: any input that starts with `prefix`
+// followed by a byte in one of the ranges of `bytes`, or `prefix` itself at
+// the end of the input if `at_eof` is true
+#[deriving(Eq, Clone)]
+pub struct Uncovered {
+    condition: ~str,
+    prefix: ~[u8],
+    bytes: ~[(u8, u8)],
+    at_eof: bool
+}
+
+impl Uncovered {
+    pub fn to_str(&self) -> ~str {
+        let mut ret = format!("{:s}: \"{:s}\"", self.condition, escape(self.prefix));
+
+        if !self.bytes.is_empty() {
+            ret.push_str(" followed by ");
+            ret.push_str(format_ranges(self.bytes));
+        }
+
+        if self.at_eof {
+            if !self.bytes.is_empty() {
+                ret.push_str(" or");
+            }
+
+            ret.push_str(" at end of input");
+        }
+
+        ret
+    }
+}
+
+// a state of the automata of the condition `condition` in which the scanner
+// may have to back up to the end of the last match: when it reaches it on
+// `example` and the input then ends or continues with a byte in one of
+// the ranges of `bytes`. `state` is its number in the emitted tables. if
+// `loop_input` is given, the scanner reads arbitrarily far before backing
+// up on `example` followed by repetitions of it, and reads as far again
+// once it resumes after the match, which makes the scanning time quadratic
+#[deriving(Eq, Clone)]
+pub struct BackingUp {
+    condition: ~str,
+    state: uint,
+    example: ~[u8],
+    bytes: ~[(u8, u8)],
+    loop_input: Option<~[u8]>
+}
+
+// formats ranges of bytes as a character class
+fn format_ranges(ranges: &[(u8, u8)]) -> ~str {
+    let mut ret = ~"[";
+
+    for &(first, last) in ranges.iter() {
+        ret.push_str(escape([first]));
+        if last != first {
+            ret.push_char('-');
+            ret.push_str(escape([last]));
+        }
+    }
+
+    ret.push_char(']');
+    ret
+}
+
+// reports `msg` as an error if `is_error` is true, in which case it is also
+// added to `errors`, or as a warning otherwise
+fn report(diag: &mut Diagnostics, errors: &mut ~[~str], is_error: bool,
+          msg: ~str, rule: Option<uint>, cond: Option<~str>) {
+    if is_error {
+        errors.push(msg.clone());
+    }
+
+    diag.report(Diagnostic {
+        level: if is_error { Error } else { Warning },
+        message: msg,
+        rule: rule,
+        condition: cond
+    });
+}
+
 pub struct Lexer {
+    // the automata and NFA of each condition, in the same order as in
+    // `conditions`. `auto` is empty for lazy lexers
     priv auto: ~[~::dfa::DFA],
-    priv actions: ~HashMap<uint, ~Action>,
-    priv conditions: ~HashMap<~str, uint>
+    priv nfas: ~[~NFA],
+
+    // the action of rule i is at index i - 1
+    priv actions: ~[~Action],
+
+    // the name of each condition and the initial state of its automata,
+    // in the order they first appear in the rules
+    priv conditions: ~[(~str, uint)],
+    priv stats: Stats,
+    priv table_type: TableType,
+    priv backend: Backend,
+    priv input: Input,
+    priv tracing: bool,
+
+    // the values of the corresponding placeholders of the template, see
+    // template.rs
+    priv lexer_type: ~str,
+    priv token_type: ~str,
+    priv prologue: ~str,
+    priv epilogue: ~str
 }
 
 impl Lexer {
-    fn print_trans_table(table: &[~[uint]], out: &mut Writer) {
+    fn print_trans_table(table: &[~[uint]], ty: &str, out: &mut Writer) {
         let mut st = 0u;
 
-        writeln!(out, "static transition_table: [[uint, ..256], ..{:u}] = [",
-                table.len());
+        writeln!(out, "static TRANSITION_TABLE: [[{:s}; 256]; {:u}] = [",
+                ty, table.len());
 
         for tb in table.iter() {
             if st != 0 {
@@ -53,12 +331,102 @@ impl Lexer {
         }
 
         writeln!(out, "\n];");
+        writeln!(out, "");
+        writeln!(out, "#[inline(always)]");
+        writeln!(out, "fn transition(st: usize, i: u8) -> usize \\{");
+        writeln!(out, "    TRANSITION_TABLE[st][i as usize] as usize");
+        writeln!(out, "\\}");
+    }
+
+    fn print_accepting_table(table: &[uint], ty: &str, out: &mut Writer) {
+        writeln!(out, "static ACCEPTING_TABLE: [{:s}; {:u}] =\n[ ",
+            ty, table.len());
+
+        let mut it = table.iter();
+        match it.next() {
+            Some(st) => write!(out, "{:u}", *st),
+            None => write!(out, "/* empty */")
+        }
+
+        let mut count = 1;
+        for st in it {
+            write!(out, ", ");
+
+            if count == 16 {
+                writeln!(out, "\n  ");
+                count = 0;
+            }
+
+            write!(out, "{:u}", *st);
+            count += 1;
+        }
+
+        writeln!(out, " ];");
+        writeln!(out, "");
+        writeln!(out, "#[inline(always)]");
+        writeln!(out, "fn accepting(st: usize) -> usize \\{");
+        writeln!(out, "    ACCEPTING_TABLE[st] as usize");
+        writeln!(out, "\\}");
     }
 
     pub fn new(regex: ~[(~str, ~str, Option<~str>)]) -> Lexer {
+        match Lexer::with_options(regex, &Options::new()) {
+            Ok(lex) => lex,
+            Err(errs) => fail!(errs.connect("\n"))
+        }
+    }
+
+    // builds a lexer, running the checks enabled in `opts`. the checks that
+    // are configured to be errors make this return the list of the errors
+    // found instead of the lexer
+    pub fn with_options(regex: ~[(~str, ~str, Option<~str>)], opts: &Options)
+        -> Result<Lexer, ~[~str]> {
+        Lexer::with_diagnostics(regex, opts, &mut StderrDiagnostics::new(Warning))
+    }
+
+    // same as `with_options`, but reports all the diagnostics emitted while
+    // building the lexer, including the errors, to `diag`
+    pub fn with_diagnostics(regex: ~[(~str, ~str, Option<~str>)], opts: &Options,
+                            diag: &mut Diagnostics) -> Result<Lexer, ~[~str]> {
+        Lexer::build(regex, true, opts, diag)
+    }
+
+    // builds a lexer from a parsed specification file, with the token type
+    // it declares, if any
+    pub fn from_spec(spec: Spec) -> Lexer {
+        let Spec { rules, token_type } = spec;
+        let mut lex = Lexer::new(rules);
+
+        match token_type {
+            Some(ty) => lex.set_token_type(ty),
+            None => ()
+        }
+
+        lex
+    }
+
+    // builds a lexer without determinizing its automata, for rule sets
+    // whose DFA would be too large to build ahead of time. such a lexer
+    // can't be written out, but can be run by the matchers returned by
+    // `lazy_matcher`
+    pub fn new_lazy(regex: ~[(~str, ~str, Option<~str>)]) -> Lexer {
+        let diag = &mut StderrDiagnostics::new(Warning);
+
+        match Lexer::build(regex, false, &Options::new(), diag) {
+            Ok(lex) => lex,
+            Err(errs) => fail!(errs.connect("\n"))
+        }
+    }
+
+    fn build(regex: ~[(~str, ~str, Option<~str>)], determinize: bool,
+             opts: &Options, diag: &mut Diagnostics) -> Result<Lexer, ~[~str]> {
         let id = &mut 0u;
-        let mut asts: ~HashMap<~str, ~[(~::regex::AST, uint)]> = ~HashMap::new();
-        let mut acts = ~HashMap::new();
+        let mut asts: ~[(~str, ~[(~::regex::AST, uint)])] = ~[];
+        let mut acts = ~[];
+        let mut errors = ~[];
+        let mut timings = Timings::new();
+        let mut cond_stats = ~[];
+        let mut start = precise_time_ns();
 
         // parse regexs and actions 
         for (reg, act, cond) in regex.move_iter() {
@@ -69,112 +437,735 @@ impl Lexer {
             };
 
             *id += 1;
+
+            // the driver would loop forever on such a rule if it didn't
+            // always consume at least one character
+            if ast.nullable() {
+                let msg = format!("rule {:u} ({:s}) matches the empty string",
+                    *id, reg);
+                report(diag, &mut errors, opts.empty_rules_are_errors, msg,
+                    Some(*id), Some(cond.clone()));
+            }
+
             let action = Action::new(reg, act);
-            acts.insert(*id, action);
+            acts.push(action);
 
-            match asts.find_mut(&cond) {
-                Some(arr) => { arr.push((ast, *id)); continue }
+            match asts.mut_iter().find(|&&(ref c, _)| *c == cond) {
+                Some(&(_, ref mut arr)) => { arr.push((ast, *id)); continue }
                 None => ()
             }
 
-            asts.insert(cond, ~[(ast, *id)]);
+            asts.push((cond, ~[(ast, *id)]));
         }
 
+        timings.parsing = precise_time_ns() - start;
+
         let mut dfas = ~[];
         let mut id = 0;
-        let mut conds = ~HashMap::new();
+        let mut conds = ~[];
+        let mut nfas = ~[];
 
         for (cond, asts) in asts.move_iter() {
-            let nfa = ::nfa::NFA::build_nfa(asts);
-            let mut dfa = ::dfa::DFA::new_from_nfa(nfa, &mut id);
-            let dfa = dfa.minimize();
+            let rules: ~[uint] = asts.iter().map(|&(_, r)| r).collect();
+            start = precise_time_ns();
+            let nfa = NFA::build_nfa(asts);
+            timings.nfa_construction += precise_time_ns() - start;
+
+            if determinize {
+                start = precise_time_ns();
+                let mut dfa = ::dfa::DFA::new_from_nfa(nfa, &mut id);
+                timings.determinization += precise_time_ns() - start;
+                start = precise_time_ns();
+
+                // look for shadowed rules before minimization since we need
+                // to know which NFA states each DFA state corresponds to
+                for &(r, by) in dfa.shadowed_rules(nfa, rules).iter() {
+                    let pat = &acts[r - 1].pattern;
+                    let msg = match by {
+                        Some(by) => format!(
+                            "rule {:u} ({:s}) in condition {:s} is shadowed \
+                             by rule {:u} ({:s}) and will never be matched",
+                            r, *pat, cond, by, acts[by - 1].pattern),
+                        None => format!(
+                            "rule {:u} ({:s}) in condition {:s} will never \
+                             be matched", r, *pat, cond)
+                    };
+
+                    report(diag, &mut errors, opts.shadowed_rules_are_errors,
+                        msg, Some(r), Some(cond.clone()));
+                }
+
+                timings.analysis += precise_time_ns() - start;
+                start = precise_time_ns();
+                let dfa_states = dfa.states_iter().count();
+                let dfa = dfa.minimize();
+                timings.minimization += precise_time_ns() - start;
+                start = precise_time_ns();
+
+                if opts.warn_quadratic_rescanning {
+                    for &(_, ref ex, _, ref lp) in dfa.backing_up_states().iter() {
+                        match *lp {
+                            Some(ref lp) => report(diag, &mut errors, false, format!(
+                                "condition {:s}: inputs made of \"{:s}\" followed by \
+                                 repetitions of \"{:s}\" force quadratic rescanning",
+                                cond, escape(*ex), escape(*lp)), None, Some(cond.clone())),
+                            None => ()
+                        }
+                    }
+                }
+
+                if opts.require_full_coverage {
+                    for (prefix, bytes, eof) in dfa.uncovered_inputs().move_iter() {
+                        let unc = Uncovered {
+                            condition: cond.clone(),
+                            prefix: prefix,
+                            bytes: bytes,
+                            at_eof: eof
+                        };
+
+                        report(diag, &mut errors, true,
+                            format!("no rule matches {:s}", unc.to_str()),
+                            None, Some(cond.clone()));
+                    }
+                }
+
+                timings.analysis += precise_time_ns() - start;
+
+                cond_stats.push(ConditionStats {
+                    name: cond.clone(),
+                    nfa_states: nfa.states_iter().count(),
+                    dfa_states: dfa_states,
+                    minimized_dfa_states: dfa.states_iter().count()
+                });
+
+                diag.report(Diagnostic {
+                    level: Debug,
+                    message: format!("initial ID of automata {:s} is {:u} ({:u})",
+                        cond, dfa.initial(), id),
+                    rule: None,
+                    condition: Some(cond.clone())
+                });
+
+                conds.push((cond.clone(), dfa.initial()));
+                dfas.push(dfa);
+            }
+
+            if !determinize {
+                conds.push((cond, 0));
+            }
+
+            nfas.push(nfa);
+        }
 
-            println!("Initial ID of automata {:s} is {:u} ({:u})", cond, dfa.initial(), id);
-            conds.insert(cond, dfa.initial());
-            dfas.push(dfa);
+        if !errors.is_empty() {
+            return Err(errors);
         }
 
-        Lexer { auto: dfas, actions: acts, conditions: conds }
+        Ok(Lexer {
+            auto: dfas,
+            nfas: nfas,
+            actions: acts,
+            conditions: conds,
+            stats: Stats {
+                conditions: cond_stats,
+                tables: None,
+                timings: timings
+            },
+            table_type: AutoType,
+            backend: TableDriven,
+            input: ReaderInput,
+            tracing: false,
+            lexer_type: ~"Lexer",
+            token_type: ~"()",
+            prologue: ~"",
+            epilogue: ~""
+        })
     }
 
-    pub fn write(&self, templ: Option<~str>, out: &mut Writer) {
-        use std::io::File;
-        use std::io::Reader;
-        use std::io::Seek;
-        use std::io;
+    // returns statistics about the automata of this lexer, their tables and
+    // the time spent building them
+    pub fn stats(&self) -> Stats {
+        let mut ret = self.stats.clone();
 
-        let templ_fname = match templ {
-            Some(s) => s,
-            None => "src/rustlex/simul.rst".into_owned()
+        if !self.auto.is_empty() {
+            let (trans_tb, finals_tb, _) = transition_table(self.auto);
+            let states = trans_tb.len();
+
+            // the same types as the ones chosen by `write`
+            let table_size = |max: uint, entries: uint| {
+                self.table_type.type_name(max).ok().map(|ty| {
+                    (ty, entries * stats::type_size(ty))
+                })
+            };
+
+            ret.tables = Some(TableStats {
+                states: states,
+                equivalence_classes: stats::equivalence_classes(trans_tb),
+                transition_table: table_size(states - 1, states * 256),
+                accepting_table: table_size(self.actions.len(), states),
+                direct_coded_ranges: direct_code::count_ranges(trans_tb),
+                direct_coded_accepting: finals_tb.iter().filter(|a| **a != 0).count()
+            });
+        }
+
+        ret
+    }
+
+    // writes a human-readable version of the statistics returned by `stats`
+    pub fn write_stats(&self, out: &mut Writer) {
+        self.stats().write(out);
+    }
+
+    // force the integer type used for the emitted transition and accepting
+    // tables instead of letting `write` choose the smallest one that fits
+    pub fn set_table_type(&mut self, ty: TableType) {
+        self.table_type = ty;
+    }
+
+    // choose between emitting the automata as tables (the default) or as
+    // directly coded `match`es
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    // choose the kind of input of the lexer emitted with the default
+    // template. a lexer reading from a slice borrows it, so its tokens may
+    // contain lexemes without copying them. a push lexer doesn't read its
+    // input itself but is fed with it, for input that comes in chunks
+    pub fn set_input(&mut self, input: Input) {
+        self.input = input;
+    }
+
+    // make the generated lexer trace its transitions, buffer refills and
+    // actions on stderr. when disabled (the default), the tracing code is
+    // compiled out of the generated lexer
+    pub fn set_tracing(&mut self, tracing: bool) {
+        self.tracing = tracing;
+    }
+
+    // set the name of the type of the generated lexer (`Lexer` by default)
+    pub fn set_lexer_type(&mut self, name: ~str) {
+        self.lexer_type = name;
+    }
+
+    // set the type of the tokens of the generated lexer (`()` by default)
+    pub fn set_token_type(&mut self, ty: ~str) {
+        self.token_type = ty;
+    }
+
+    // set user code to emit before and after the driver of the generated
+    // lexer, e.g. `use` declarations and helper functions
+    pub fn set_prologue(&mut self, code: ~str) {
+        self.prologue = code;
+    }
+
+    pub fn set_epilogue(&mut self, code: ~str) {
+        self.epilogue = code;
+    }
+
+    // returns a matcher for the rules of the condition `cond` that builds
+    // the states of the DFA on demand while scanning, keeping at most
+    // `cache_size` of them
+    pub fn lazy_matcher<'a>(&'a self, cond: &str, cache_size: uint)
+        -> Option<LazyDFA<'a>> {
+        self.condition_index(cond).map(|i| LazyDFA::new(&*self.nfas[i], cache_size))
+    }
+
+    // runs the NFA of the condition `cond` on `input`. returns the rule
+    // that matches the longest prefix of the input and the length of this
+    // prefix. this doesn't need the automata to be determinized
+    pub fn simulate(&self, cond: &str, input: &[u8]) -> Option<(uint, uint)> {
+        match self.condition_index(cond) {
+            Some(i) => self.nfas[i].longest_match(input),
+            None => None
+        }
+    }
+
+    // checks that the DFA of the condition `cond` agrees with its NFA on
+    // the longest match of each suffix of `input`. returns the offset of
+    // the first suffix on which they disagree, if any
+    pub fn check_against_nfa(&self, cond: &str, input: &[u8]) -> Option<uint> {
+        let mut interp = self.interpreter();
+        if !interp.set_condition(cond) {
+            fail!("No such condition: {:s}", cond);
+        }
+
+        for pos in range(0, input.len()) {
+            let nfa_match = self.simulate(cond, input.slice_from(pos))
+                .map(|(act, len)| (act, pos + len));
+
+            if nfa_match != interp.longest_match(input, pos) {
+                return Some(pos);
+            }
+        }
+
+        None
+    }
+
+    // the index of the condition `cond` in `conditions`, `auto` and `nfas`
+    fn condition_index(&self, cond: &str) -> Option<uint> {
+        self.conditions.iter().position(|&(ref c, _)| c.as_slice() == cond)
+    }
+
+    // the DFA of the condition `cond`, or None if there is no such
+    // condition or if this lexer was built with `new_lazy`
+    fn condition_dfa<'a>(&'a self, cond: &str) -> Option<&'a ::dfa::DFA> {
+        if self.is_lazy() {
+            return None;
+        }
+
+        self.condition_index(cond).map(|i| &*self.auto[i])
+    }
+
+    // whether this lexer was built with `new_lazy`, in which case it has no
+    // DFA to analyse
+    fn is_lazy(&self) -> bool {
+        self.auto.is_empty() && !self.nfas.is_empty()
+    }
+
+    // the sorted names of the conditions of this lexer or of `other`. fails
+    // if either lexer was built with `new_lazy`, since comparing lexers
+    // needs their DFA
+    fn all_conditions(&self, other: &Lexer) -> ~[~str] {
+        if self.is_lazy() || other.is_lazy() {
+            fail!("Can't compare a lexer built with Lexer::new_lazy");
+        }
+
+        let mut conds: ~[~str] = self.conditions.iter().map(|&(ref c, _)| c.clone()).collect();
+
+        for &(ref c, _) in other.conditions.iter() {
+            if !conds.contains(c) {
+                conds.push(c.clone());
+            }
+        }
+
+        conds.sort();
+        conds
+    }
+
+    // whether the rules `l` of this lexer and `r` of `other` execute the
+    // same action. None stands for the default action
+    fn same_action(&self, other: &Lexer, l: Option<uint>, r: Option<uint>) -> bool {
+        match (l, r) {
+            (None, None) => true,
+            (Some(l), Some(r)) => self.actions[l - 1].action ==
+                other.actions[r - 1].action,
+            _ => false
+        }
+    }
+
+    // looks, in each condition of either lexer, for the shortest input on
+    // which `differ` holds for the rules both lexers tokenize it as
+    fn find_difference(&self, other: &Lexer,
+                       differ: |Option<uint>, Option<uint>| -> bool)
+        -> Option<Difference> {
+        for cond in self.all_conditions(other).move_iter() {
+            let a = self.condition_dfa(cond);
+            let b = other.condition_dfa(cond);
+
+            match find_difference(a, b, |l, r| differ(l, r)) {
+                Some((input, l, r)) => return Some(Difference {
+                    condition: cond,
+                    input: input,
+                    left: l,
+                    right: r
+                }),
+                None => ()
+            }
+        }
+
+        None
+    }
+
+    // checks whether this lexer and `other` tokenize every input the same
+    // way, i.e. if in each condition they match the same lexemes and execute
+    // the same action for each of them. returns None if they do, and one of
+    // the shortest inputs on which they disagree otherwise. fails if either
+    // lexer was built with `new_lazy`
+    pub fn compare(&self, other: &Lexer) -> Option<Difference> {
+        self.find_difference(other, |l, r| !self.same_action(other, l, r))
+    }
+
+    // checks whether every lexeme matched by this lexer is also matched by
+    // `other`, which executes the same action for it. `other` may match
+    // more lexemes. returns None if that's the case, and one of the shortest
+    // counter-examples otherwise. fails if either lexer was built with
+    // `new_lazy`
+    pub fn included_in(&self, other: &Lexer) -> Option<Difference> {
+        self.find_difference(other, |l, r| {
+            l.is_some() && !self.same_action(other, l, r)
+        })
+    }
+
+    // lists all the ways `other` tokenizes inputs differently from this
+    // lexer: in each condition, for each pair of rules such that some input
+    // is a lexeme of the first one in this lexer and of the second one in
+    // `other`, and the rules don't have the same action, returns up to `n`
+    // of the shortest such inputs. None stands for inputs that aren't a
+    // lexeme of any rule. fails if either lexer was built with `new_lazy`
+    pub fn diff(&self, other: &Lexer, n: uint)
+        -> ~[(~str, Option<uint>, Option<uint>, ~[~[u8]])] {
+        let mut ret = ~[];
+
+        for cond in self.all_conditions(other).move_iter() {
+            let a = self.condition_dfa(cond);
+            let b = other.condition_dfa(cond);
+
+            let diffs = find_differences(a, b, n, |l, r| !self.same_action(other, l, r));
+            for (l, r, inputs) in diffs.move_iter() {
+                ret.push((cond.clone(), l, r, inputs));
+            }
+        }
+
+        ret
+    }
+
+    // writes a human-readable report of the differences between the
+    // languages of the rules of this lexer, considered as the old version,
+    // and of `other`, considered as the new one
+    pub fn write_diff_report(&self, other: &Lexer, n: uint, out: &mut Writer) {
+        fn describe(lex: &Lexer, rule: uint) -> ~str {
+            format!("rule {:u} ({:s})", rule, lex.actions[rule - 1].pattern)
+        }
+
+        let diffs = self.diff(other, n);
+
+        if diffs.is_empty() {
+            writeln!(out, "Both lexers are equivalent");
+            return;
+        }
+
+        for &(ref cond, l, r, ref inputs) in diffs.iter() {
+            match (l, r) {
+                (None, Some(r)) => writeln!(out, "{:s}: new {:s} now matches:",
+                    *cond, describe(other, r)),
+                (Some(l), None) => writeln!(out, "{:s}: old {:s} no longer matches:",
+                    *cond, describe(self, l)),
+                (Some(l), Some(r)) => writeln!(out,
+                    "{:s}: lexemes of old {:s} are now matched by new {:s}:",
+                    *cond, describe(self, l), describe(other, r)),
+                (None, None) => ()
+            }
+
+            for input in inputs.iter() {
+                writeln!(out, "    \"{:s}\"", escape(*input));
+            }
+        }
+    }
+
+    // returns, for each rule that can be matched, the name of its condition
+    // and its `n` shortest inputs that are tokenized as exactly one lexeme
+    // matched by this rule. the result is sorted by rule number. fails for
+    // a lexer built with `new_lazy`
+    pub fn witnesses(&self, n: uint) -> ~[(uint, ~str, ~[~[u8]])] {
+        if self.is_lazy() {
+            fail!("Can't find witnesses for a lexer built with Lexer::new_lazy");
+        }
+
+        let mut ret = ~[];
+
+        for (&(ref cond, _), dfa) in self.conditions.iter().zip(self.auto.iter()) {
+            for (rule, inputs) in dfa.witnesses(n).move_iter() {
+                ret.push((rule, cond.clone(), inputs));
+            }
+        }
+
+        ret.sort();
+        ret
+    }
+
+    // lists, for each condition, the inputs on which no rule matches and the
+    // default action applies. fails for a lexer built with `new_lazy`
+    pub fn uncovered_inputs(&self) -> ~[Uncovered] {
+        if self.is_lazy() {
+            fail!("Can't check the coverage of a lexer built with Lexer::new_lazy");
+        }
+
+        let mut ret = ~[];
+
+        for (&(ref cond, _), dfa) in self.conditions.iter().zip(self.auto.iter()) {
+            for (prefix, bytes, eof) in dfa.uncovered_inputs().move_iter() {
+                ret.push(Uncovered {
+                    condition: cond.clone(),
+                    prefix: prefix,
+                    bytes: bytes,
+                    at_eof: eof
+                });
+            }
+        }
+
+        ret
+    }
+
+    // writes a human-readable report of the inputs on which the default
+    // action applies
+    pub fn write_coverage_report(&self, out: &mut Writer) {
+        let uncovered = self.uncovered_inputs();
+
+        if uncovered.is_empty() {
+            writeln!(out, "All inputs are matched by some rule");
+        }
+
+        for unc in uncovered.iter() {
+            writeln!(out, "no rule matches {:s}", unc.to_str());
+        }
+    }
+
+    // lists, for each condition, the states in which the scanner may have
+    // to back up, as flex -b does. fails for a lexer built with `new_lazy`
+    pub fn backing_up(&self) -> ~[BackingUp] {
+        if self.is_lazy() {
+            fail!("Can't find the backing up states of a lexer built with Lexer::new_lazy");
+        }
+
+        let (_, _, new_ids) = transition_table(self.auto);
+        let mut ret = ~[];
+
+        for (&(ref cond, _), dfa) in self.conditions.iter().zip(self.auto.iter()) {
+            for (st, ex, bytes, lp) in dfa.backing_up_states().move_iter() {
+                ret.push(BackingUp {
+                    condition: cond.clone(),
+                    state: *new_ids.find(&st).unwrap(),
+                    example: ex,
+                    bytes: bytes,
+                    loop_input: lp
+                });
+            }
+        }
+
+        ret
+    }
+
+    // writes a human-readable report of the states that require backing up
+    pub fn write_backing_up_report(&self, out: &mut Writer) {
+        let states = self.backing_up();
+
+        if states.is_empty() {
+            writeln!(out, "No backing up");
+        }
+
+        for b in states.iter() {
+            writeln!(out, "{:s}: state {:u} is non-accepting, e.g. on \"{:s}\"",
+                b.condition, b.state, escape(b.example));
+
+            if b.bytes.is_empty() {
+                writeln!(out, "    backs up at end of input");
+            } else {
+                writeln!(out, "    backs up on {:s} or at end of input",
+                    format_ranges(b.bytes));
+            }
+
+            match b.loop_input {
+                Some(ref lp) => writeln!(out, "    quadratic rescanning on \
+                    repetitions of \"{:s}\"", escape(*lp)),
+                None => ()
+            }
+        }
+    }
+
+    // returns a generator of random inputs for the rules of the condition
+    // `cond`, or None if there is no such condition or if this lexer was
+    // built with `new_lazy`
+    pub fn fuzzer<'a>(&'a self, cond: &str) -> Option<Fuzzer<'a>> {
+        self.condition_dfa(cond).map(|dfa| Fuzzer::new(dfa))
+    }
+
+    // finds all pairs of rules of a same condition that can match the same
+    // lexeme, where the priority of the first one decides which one wins
+    pub fn ambiguities(&self) -> ~[Ambiguity] {
+        let mut ret = ~[];
+
+        for (&(ref cond, _), nfa) in self.conditions.iter().zip(self.nfas.iter()) {
+            // this needs the non-minimized automata since we need to know
+            // which NFA states each of its states correspond to
+            let mut id = 0;
+            let dfa = ::dfa::DFA::new_from_nfa(*nfa, &mut id);
+
+            for (a, b, input) in dfa.overlapping_rules(*nfa).move_iter() {
+                ret.push(Ambiguity {
+                    condition: cond.clone(),
+                    first: a,
+                    second: b,
+                    example: input
+                });
+            }
+        }
+
+        ret
+    }
+
+    // writes a human-readable report of the ambiguities of this lexer
+    pub fn write_ambiguity_report(&self, out: &mut Writer) {
+        for amb in self.ambiguities().iter() {
+            let first = &self.actions[amb.first - 1];
+            let second = &self.actions[amb.second - 1];
+
+            writeln!(out, "{:s}: rule {:u} ({:s}) wins over rule {:u} ({:s}), \
+                e.g. on \"{:s}\"", amb.condition, amb.first, first.pattern,
+                amb.second, second.pattern, escape(amb.example));
+        }
+    }
+
+    // returns an interpreter that runs the automata of this lexer on input
+    // directly, without generating code
+    pub fn interpreter(&self) -> Interpreter {
+        if self.is_lazy() {
+            fail!("Can't interpret a lexer built with Lexer::new_lazy");
+        }
+
+        let (trans_tb, finals_tb, new_ids) = transition_table(self.auto);
+        let mut conds = ~HashMap::new();
+
+        for &(ref cond, init_s) in self.conditions.iter() {
+            conds.insert(cond.clone(), *new_ids.find(&init_s).unwrap());
+        }
+
+        Interpreter::new(trans_tb, finals_tb, conds)
+    }
+
+    // writes the lexer using the template file `templ`, or the default
+    // driver template if None. returns an error if the template can't be
+    // loaded, or if the entries of the tables don't fit in the forced table
+    // type
+    pub fn write(&self, templ: Option<~str>, out: &mut Writer) -> Result<(), ~str> {
+        if self.is_lazy() {
+            return Err(~"can't write a lexer built with Lexer::new_lazy");
+        }
+
+        let buf = match templ {
+            Some(fname) => match load_template(fname) {
+                Ok(buf) => buf,
+                Err(e) => return Err(e)
+            },
+            None => match self.input {
+                ReaderInput => READER_TEMPLATE.as_bytes().to_owned(),
+                SliceInput => SLICE_TEMPLATE.as_bytes().to_owned(),
+                PushInput => PUSH_TEMPLATE.as_bytes().to_owned()
+            }
         };
 
-        let pth = Path::new(templ_fname);
-        let mut inp = match File::open(&pth) {
+        let contents = match ::std::str::from_utf8_opt(buf) {
             Some(s) => s,
-            None => fail!("Unable to open template file")
+            None => return Err(~"template is not valid UTF-8")
         };
 
-        // get file size
-        inp.seek(0, io::SeekEnd);
-        let size = inp.tell();
-        inp.seek(0, io::SeekSet);
-
-        let mut buf = ::std::vec::from_elem(size as uint, 0 as u8);
-        inp.read(buf);
+        let pieces = match parse_template(contents) {
+            Ok(p) => p,
+            Err(e) => return Err(e)
+        };
 
-        let contents = ::std::str::from_utf8(buf);
         let (trans_tb, finals_tb, new_ids) = transition_table(self.auto);
 
-        for line in contents.lines() {
-            if line == "#RUSTLEX_TRANSITION_TABLE" {
-                Lexer::print_trans_table(trans_tb, out);
-            }
+        // states are numbered from 0 (the dead state) to len - 1, and
+        // actions are numbered from 1, 0 meaning non-accepting
+        let state_ty = match self.table_type.type_name(trans_tb.len() - 1) {
+            Ok(ty) => ty,
+            Err(e) => return Err(e)
+        };
+
+        let action_ty = match self.table_type.type_name(self.actions.len()) {
+            Ok(ty) => ty,
+            Err(e) => return Err(e)
+        };
+
+        for piece in pieces.iter() {
+            let (p, whole_line) = match *piece {
+                Text(ref s) => { out.write(s.as_bytes()); continue }
+                Hole(p, whole_line) => (p, whole_line)
+            };
+
+            let mut exp = MemWriter::new();
+
+            {
+                let w = &mut exp as &mut Writer;
 
-            else if line == "#RUSTLEX_ACCEPTING_TABLE" {
-                writeln!(out, "static accepting: [uint, .. {:u}] =\n[ ",
-                    finals_tb.len());
+                match p {
+                    TransitionTable => match self.backend {
+                        TableDriven =>
+                            Lexer::print_trans_table(trans_tb, state_ty, w),
+                        DirectCoded =>
+                            direct_code::print_transition_code(trans_tb, w)
+                    },
 
-                let mut it = finals_tb.iter();
-                match it.next() {
-                    Some(st) => write!(out, "{:u}", *st),
-                    None => {
-                        writeln!(out, "/* empty */ ]");
-                        continue
+                    AcceptingTable => match self.backend {
+                        TableDriven =>
+                            Lexer::print_accepting_table(finals_tb, action_ty, w),
+                        DirectCoded =>
+                            direct_code::print_accepting_code(finals_tb, w)
+                    },
+
+                    StateActions => {
+                        for (i, action) in self.actions.iter().enumerate() {
+                            action.write(i + 1, w);
+                        }
                     }
-                }
 
-                let mut count = 1;
-                for st in it {
-                    write!(out, ", ");
+                    Conditions => {
+                        for &(ref cond, init_s) in self.conditions.iter() {
+                            writeln!(w, "#[allow(non_upper_case_globals, dead_code)]");
+                            writeln!(w, "const {:s}: usize = {:u};",
+                                *cond, *new_ids.find(&init_s).unwrap());
+                        }
+                    }
+
+                    ConditionEnum => {
+                        writeln!(w, "#[allow(dead_code)]");
+                        writeln!(w, "#[derive(Clone, Copy, PartialEq, Eq, Debug)]");
+                        writeln!(w, "pub enum Condition \\{");
+
+                        for &(ref cond, _) in self.conditions.iter() {
+                            writeln!(w, "    {:s},", *cond);
+                        }
+
+                        writeln!(w, "\\}\n");
+
+                        // so that actions can switch conditions by name
+                        writeln!(w, "#[allow(unused_imports)]");
+                        writeln!(w, "use self::Condition::*;\n");
+
+                        writeln!(w, "fn initial_state(cond: Condition) -> usize \\{");
+                        writeln!(w, "    match cond \\{");
+
+                        for &(ref cond, init_s) in self.conditions.iter() {
+                            writeln!(w, "        Condition::{:s} => {:u},",
+                                *cond, *new_ids.find(&init_s).unwrap());
+                        }
 
-                    if count == 16 {
-                        writeln!(out, "\n  ");
-                        count = 0;
+                        writeln!(w, "    \\}");
+                        writeln!(w, "\\}");
                     }
 
-                    write!(out, "{:u}", *st);
-                    count += 1;
-                }
+                    Support => write!(w, "{:s}", SUPPORT_CODE),
 
-                writeln!(out, " ];");
-            }
+                    Trace => {
+                        writeln!(w, "macro_rules! rustlex_trace \\{");
 
-            else if line == "#RUSTLEX_STATE_ACTIONS" {
-                for (i, action) in self.actions.iter() { 
-                    action.write(*i, out);
-                }
-            }
+                        if self.tracing {
+                            writeln!(w, "    ($($arg:tt)*) => (eprintln!($($arg)*))");
+                        } else {
+                            writeln!(w, "    ($($arg:tt)*) => (())");
+                        }
 
-            else if line == "#RUSTLEX_CONDITIONS" {
-                for (cond, init_s) in self.conditions.iter() {
-                    writeln!(out, "static {:s}: uint = {:u};",
-                        *cond, *new_ids.find(init_s).unwrap());
+                        writeln!(w, "\\}");
+                    }
+
+                    LexerType => write!(w, "{:s}", self.lexer_type),
+                    TokenType => write!(w, "{:s}", self.token_type),
+                    StateType => write!(w, "{:s}", state_ty),
+                    ActionType => write!(w, "{:s}", action_ty),
+                    Prologue => write!(w, "{:s}", self.prologue),
+                    Epilogue => write!(w, "{:s}", self.epilogue)
                 }
             }
 
-            else {
-                writeln!(out, "{:s}", line);
+            let exp = exp.inner();
+            out.write(exp);
+
+            if whole_line && !exp.is_empty() && !exp.ends_with(bytes!("\n")) {
+                out.write(bytes!("\n"));
             }
-        } 
+        }
+
+        Ok(())
     }
 }
@@ -9,9 +9,18 @@ pub use lexer::Lexer;
 mod action;
 mod automata;
 mod dfa;
+pub mod diagnostics;
+mod direct_code;
+mod equiv;
+pub mod fuzz;
+pub mod interp;
+pub mod lazy;
 pub mod lexer;
 mod nfa;
 mod regex;
+pub mod spec;
+pub mod stats;
+pub mod template;
 mod trans_table;
 
 // allow this function to be unused since it's here for debugging purposes
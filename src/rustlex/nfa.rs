@@ -351,5 +351,84 @@ impl NFA {
 
         false
     }
-}
 
+    // the set of states the automata is in before reading any input
+    pub fn initial_states(&self) -> ~HashSet<uint> {
+        self.eclosure_(self.initial)
+    }
+
+    // the set of states reachable from `st` by reading the byte `c`,
+    // including the states reachable from those by epsilon-transitions
+    pub fn step(&self, st: &HashSet<uint>, c: u8) -> ~HashSet<uint> {
+        let mut moved = ~HashSet::new();
+
+        for i in st.iter() {
+            match self.states.find(i) {
+                Some(state) => match state.trans.find(&c) {
+                    Some(dsts) => {
+                        for d in dsts.iter() {
+                            moved.insert(*d);
+                        }
+                    }
+                    None => ()
+                },
+                None => ()
+            }
+        }
+
+        self.eclosure(moved)
+    }
+
+    // the action to execute when the automata stops in the set of states
+    // `st`. as in the DFA, when several rules match, the one that was given
+    // first (that has the lowest action number) wins
+    pub fn action(&self, st: &HashSet<uint>) -> Option<uint> {
+        let mut action = None;
+
+        for i in st.iter() {
+            match self.states.find(i) {
+                Some(state) => match (state.action, action) {
+                    (Some(act), None) => action = Some(act),
+                    (Some(act), Some(a)) if act < a => action = Some(act),
+                    _ => ()
+                },
+                None => ()
+            }
+        }
+
+        action
+    }
+
+    // simulates the automata on `input` from the set of states `st`,
+    // starting at the offset `pos`. returns the action of the rule that
+    // matches the longest prefix of the remaining input, and the offset
+    // of the end of this match
+    pub fn longest_match_from(&self, st: ~HashSet<uint>, input: &[u8], pos: uint)
+        -> Option<(uint, uint)> {
+        let mut current = st;
+        let mut last_match = None;
+        let mut pos = pos;
+
+        while pos < input.len() {
+            current = self.step(current, input[pos]);
+
+            if current.is_empty() {
+                break;
+            }
+
+            pos += 1;
+            match self.action(current) {
+                Some(act) => last_match = Some((act, pos)),
+                None => ()
+            }
+        }
+
+        last_match
+    }
+
+    // the action of the rule that matches the longest non-empty prefix of
+    // `input`, and the length of this prefix
+    pub fn longest_match(&self, input: &[u8]) -> Option<(uint, uint)> {
+        self.longest_match_from(self.initial_states(), input, 0)
+    }
+}
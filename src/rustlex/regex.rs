@@ -12,6 +12,16 @@ pub enum AST {
 }
 
 impl AST {
+    // whether this expression matches the empty string
+    pub fn nullable(&self) -> bool {
+        match *self {
+            Or(ref opl, ref opr) => opl.nullable() || opr.nullable(),
+            Cat(ref opl, ref opr) => opl.nullable() && opr.nullable(),
+            Clos(_) => true,
+            Char(_) => false
+        }
+    }
+
     unsafe fn new_from_c_ast(a: &RustlexCAST) -> ~AST {
         if a.ty == RustlexOr as i32 {
             let opl = AST::new_from_c_ast(&*a.op_left);
@@ -0,0 +1,94 @@
+/*
+ * Parser for lexer specification files, so that lexers can be described
+ * outside of Rust code, e.g. for the tools that compare two versions of a
+ * lexer. Each line that is neither empty nor starts with `#` is a rule. It
+ * is made of an optional condition between angle brackets, the regular
+ * expression, which ends at the first whitespace that is neither escaped
+ * nor in a character class, and the code of the action, which spans to the
+ * end of the line. Lines starting with `%` are directives, so a regular
+ * expression starting with `%` must escape it. The only directive is
+ * `%token`, which declares the type of the tokens the actions of the
+ * generated lexer return:
+ *
+ *     %token              Tok
+ *     [0-9]+              return Some(Tok::Number);
+ *     <Comment>\*/        self.condition = Initial;
+ */
+
+pub struct Spec {
+    rules: ~[(~str, ~str, Option<~str>)],
+    token_type: Option<~str>
+}
+
+pub fn parse_spec(spec: &str) -> Result<Spec, ~str> {
+    let mut ret = ~[];
+    let mut token_type = None;
+
+    for (num, line) in spec.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with("#") {
+            continue;
+        }
+
+        if line.starts_with("%") {
+            let end = match line.find(|c: char| c.is_whitespace()) {
+                Some(i) => i,
+                None => line.len()
+            };
+
+            let (name, arg) = (line.slice_to(end), line.slice_from(end).trim());
+            if name != "%token" {
+                return Err(format!("line {:u}: unknown directive {:s}", num + 1, name));
+            }
+
+            if arg.is_empty() {
+                return Err(format!("line {:u}: missing token type", num + 1));
+            }
+
+            token_type = Some(arg.to_owned());
+            continue;
+        }
+
+        let (cond, rest) = if line.starts_with("<") {
+            match line.find('>') {
+                Some(end) => (Some(line.slice(1, end).to_owned()),
+                              line.slice_from(end + 1)),
+                None => return Err(format!("line {:u}: unterminated condition",
+                    num + 1))
+            }
+        } else {
+            (None, line)
+        };
+
+        // find the end of the regular expression
+        let mut end = rest.len();
+        let mut escaped = false;
+        let mut in_class = false;
+
+        for (i, c) in rest.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '[' {
+                in_class = true;
+            } else if c == ']' {
+                in_class = false;
+            } else if c.is_whitespace() && !in_class {
+                end = i;
+                break;
+            }
+        }
+
+        let regex = rest.slice_to(end);
+        if regex.is_empty() {
+            return Err(format!("line {:u}: missing regular expression", num + 1));
+        }
+
+        let action = rest.slice_from(end).trim();
+        ret.push((regex.to_owned(), action.to_owned(), cond));
+    }
+
+    Ok(Spec { rules: ret, token_type: token_type })
+}
@@ -0,0 +1,135 @@
+use std::io::Writer;
+
+/*
+ * Statistics about the compilation of a lexer: size of the automata of each
+ * condition, size of the code emitted for them by each backend, and time
+ * spent in each phase of the compilation.
+ */
+
+#[deriving(Clone)]
+pub struct ConditionStats {
+    name: ~str,
+    nfa_states: uint,
+    dfa_states: uint,
+    minimized_dfa_states: uint
+}
+
+// size of the code emitted for the automata by each backend
+#[deriving(Clone)]
+pub struct TableStats {
+    // total number of states in the tables, including the dead state
+    states: uint,
+
+    // number of classes of bytes that have the same transitions in every
+    // state
+    equivalence_classes: uint,
+
+    // (type of the entries, size in bytes) of the transition and accepting
+    // tables emitted by the table-driven backend, or None if the entries
+    // don't fit in the forced table type
+    transition_table: Option<(&'static str, uint)>,
+    accepting_table: Option<(&'static str, uint)>,
+
+    // number of byte ranges matched and of accepting states listed by the
+    // functions emitted by the direct-coded backend
+    direct_coded_ranges: uint,
+    direct_coded_accepting: uint
+}
+
+// time spent in each phase, in nanoseconds
+#[deriving(Clone)]
+pub struct Timings {
+    parsing: u64,
+    nfa_construction: u64,
+    determinization: u64,
+    analysis: u64,
+    minimization: u64
+}
+
+#[deriving(Clone)]
+pub struct Stats {
+    conditions: ~[ConditionStats],
+    tables: Option<TableStats>,
+    timings: Timings
+}
+
+impl Timings {
+    pub fn new() -> Timings {
+        Timings {
+            parsing: 0,
+            nfa_construction: 0,
+            determinization: 0,
+            analysis: 0,
+            minimization: 0
+        }
+    }
+}
+
+// size in bytes of an integer type used for table entries
+pub fn type_size(ty: &str) -> uint {
+    match ty {
+        "u8" => 1,
+        "u16" => 2,
+        "u32" => 4,
+        _ => ::std::mem::size_of::<uint>()
+    }
+}
+
+// counts the classes of bytes that have the same transitions in every state
+// of a transition table
+pub fn equivalence_classes(table: &[~[uint]]) -> uint {
+    let mut classes: ~[~[uint]] = ~[];
+
+    for c in range(0u, 256) {
+        let column: ~[uint] = table.iter().map(|row| row[c]).collect();
+
+        if !classes.contains(&column) {
+            classes.push(column);
+        }
+    }
+
+    classes.len()
+}
+
+impl Stats {
+    pub fn write(&self, out: &mut Writer) {
+        for cond in self.conditions.iter() {
+            writeln!(out, "condition {:s}: {:u} NFA states, {:u} DFA states, \
+                {:u} after minimization", cond.name, cond.nfa_states,
+                cond.dfa_states, cond.minimized_dfa_states);
+        }
+
+        match self.tables {
+            Some(ref tb) => {
+                writeln!(out, "{:u} states in tables, {:u} equivalence classes",
+                    tb.states, tb.equivalence_classes);
+
+                match tb.transition_table {
+                    Some((ty, size)) => writeln!(out, "transition table of {:s}: \
+                        {:u} bytes", ty, size),
+                    None => writeln!(out, "transition table: entries don't fit \
+                        in the forced table type")
+                }
+
+                match tb.accepting_table {
+                    Some((ty, size)) => writeln!(out, "accepting table of {:s}: \
+                        {:u} bytes", ty, size),
+                    None => writeln!(out, "accepting table: entries don't fit \
+                        in the forced table type")
+                }
+
+                writeln!(out, "direct-coded: {:u} byte ranges, {:u} accepting states",
+                    tb.direct_coded_ranges, tb.direct_coded_accepting);
+            }
+
+            None => ()
+        }
+
+        let t = &self.timings;
+        writeln!(out, "parsing: {:u} us", (t.parsing / 1000) as uint);
+        writeln!(out, "NFA construction: {:u} us", (t.nfa_construction / 1000) as uint);
+        writeln!(out, "determinization: {:u} us", (t.determinization / 1000) as uint);
+        writeln!(out, "analysis: {:u} us", (t.analysis / 1000) as uint);
+        writeln!(out, "minimization: {:u} us", (t.minimization / 1000) as uint);
+    }
+}
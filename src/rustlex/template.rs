@@ -0,0 +1,165 @@
+/*
+ * Templates for the code emitted by Lexer::write. A template is the Rust
+ * code of the driver of the lexer, in which placeholders are replaced by
+ * the generated parts. A placeholder is `#RUSTLEX_` followed by its name,
+ * made of uppercase letters, digits and underscores. It may appear anywhere
+ * in a line, e.g. `struct #RUSTLEX_LEXER_TYPE {`. When a placeholder is
+ * alone on its line, the whole line is replaced. The placeholders are:
+ *
+ *   TRANSITION_TABLE  the `transition(state, byte)` function (required)
+ *   ACCEPTING_TABLE   the `accepting(state)` function (required)
+ *   STATE_ACTIONS     the arms of a `match` on the action number that run
+ *                     the code of each rule (required)
+ *   CONDITIONS        a constant per condition holding its initial state
+ *   CONDITION_ENUM    a `Condition` enum with a variant per condition, and
+ *                     the `initial_state(Condition)` function
+ *   TRACE             the `rustlex_trace!` macro
+ *   SUPPORT           the types shared by the default drivers: `Position`,
+ *                     `Span`, `Spanned` and `LexError`
+ *   LEXER_TYPE        the name of the lexer type
+ *   TOKEN_TYPE        the type of the tokens
+ *   STATE_TYPE        the integer type of the states in the tables
+ *   ACTION_TYPE       the integer type of the actions in the tables
+ *   PROLOGUE          user code to put before the driver
+ *   EPILOGUE          user code to put after the driver
+ *
+ * A template must also contain either CONDITIONS or CONDITION_ENUM. Unknown
+ * placeholders are errors.
+ */
+
+static PREFIX: &'static str = "#RUSTLEX_";
+
+#[deriving(Eq, Clone)]
+pub enum Placeholder {
+    TransitionTable,
+    AcceptingTable,
+    StateActions,
+    Conditions,
+    ConditionEnum,
+    Trace,
+    Support,
+    LexerType,
+    TokenType,
+    StateType,
+    ActionType,
+    Prologue,
+    Epilogue
+}
+
+impl Placeholder {
+    fn from_name(name: &str) -> Option<Placeholder> {
+        match name {
+            "TRANSITION_TABLE" => Some(TransitionTable),
+            "ACCEPTING_TABLE" => Some(AcceptingTable),
+            "STATE_ACTIONS" => Some(StateActions),
+            "CONDITIONS" => Some(Conditions),
+            "CONDITION_ENUM" => Some(ConditionEnum),
+            "TRACE" => Some(Trace),
+            "SUPPORT" => Some(Support),
+            "LEXER_TYPE" => Some(LexerType),
+            "TOKEN_TYPE" => Some(TokenType),
+            "STATE_TYPE" => Some(StateType),
+            "ACTION_TYPE" => Some(ActionType),
+            "PROLOGUE" => Some(Prologue),
+            "EPILOGUE" => Some(Epilogue),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TransitionTable => "TRANSITION_TABLE",
+            AcceptingTable => "ACCEPTING_TABLE",
+            StateActions => "STATE_ACTIONS",
+            Conditions => "CONDITIONS",
+            ConditionEnum => "CONDITION_ENUM",
+            Trace => "TRACE",
+            Support => "SUPPORT",
+            LexerType => "LEXER_TYPE",
+            TokenType => "TOKEN_TYPE",
+            StateType => "STATE_TYPE",
+            ActionType => "ACTION_TYPE",
+            Prologue => "PROLOGUE",
+            Epilogue => "EPILOGUE"
+        }
+    }
+}
+
+pub enum Piece {
+    // text copied as is to the output
+    Text(~str),
+
+    // a placeholder, and whether it is alone on its line
+    Hole(Placeholder, bool)
+}
+
+fn is_name_char(c: char) -> bool {
+    (c >= 'A' && c <= 'Z') || (c >= '0' && c <= '9') || c == '_'
+}
+
+// splits `templ` into text and placeholders. returns an error if the
+// template contains unknown placeholders or misses required ones
+pub fn parse_template(templ: &str) -> Result<~[Piece], ~str> {
+    let mut ret = ~[];
+    let mut found = ~[];
+
+    // the text is kept exactly as it is, including the newline at the end
+    // of each line, if any
+    let lines: ~[&str] = templ.split('\n').collect();
+
+    for (num, &line) in lines.iter().enumerate() {
+        let newline = if num + 1 < lines.len() { "\n" } else { "" };
+        let mut rest = line;
+
+        loop {
+            let start = match rest.find_str(PREFIX) {
+                Some(i) => i,
+                None => {
+                    if !rest.is_empty() || !newline.is_empty() {
+                        ret.push(Text(rest.to_owned() + newline));
+                    }
+
+                    break;
+                }
+            };
+
+            let after = rest.slice_from(start + PREFIX.len());
+            let len = match after.find(|c: char| !is_name_char(c)) {
+                Some(l) => l,
+                None => after.len()
+            };
+
+            let name = after.slice_to(len);
+            let p = match Placeholder::from_name(name) {
+                Some(p) => p,
+                None => return Err(format!("line {:u}: unknown placeholder {:s}{:s}",
+                    num + 1, PREFIX, name))
+            };
+
+            found.push(p);
+
+            // a placeholder alone on its line replaces the whole line
+            if line.trim().len() == PREFIX.len() + len {
+                ret.push(Hole(p, true));
+                break;
+            }
+
+            ret.push(Text(rest.slice_to(start).to_owned()));
+            ret.push(Hole(p, false));
+            rest = after.slice_from(len);
+        }
+    }
+
+    for p in [TransitionTable, AcceptingTable, StateActions].iter() {
+        if !found.contains(p) {
+            return Err(format!("missing placeholder {:s}{:s}", PREFIX, p.name()));
+        }
+    }
+
+    if !found.contains(&Conditions) && !found.contains(&ConditionEnum) {
+        return Err(format!("missing placeholder {:s}CONDITIONS or {:s}CONDITION_ENUM",
+            PREFIX, PREFIX));
+    }
+
+    Ok(ret)
+}
@@ -2,9 +2,7 @@ extern mod rustlex;
 
 use rustlex::Lexer;
 
-#[test]
-fn main() {
-    use std::io;
+fn rules() -> ~[(~str, ~str, Option<~str>)] {
     let mut regexps = ~[];
 
     regexps.push((~"a", stringify!(println!("Saw an A")).into_owned(), None));
@@ -12,9 +10,704 @@ fn main() {
     regexps.push((~"a*bb*", stringify!(println!("Saw a*b+")).into_owned(), None));
     regexps.push((~"a*bb*", stringify!(println!("Saw a*b+")).into_owned(), Some("Foo".into_owned())));
 
-    let lex = ~Lexer::new(regexps);
+    regexps
+}
+
+#[test]
+fn main() {
+    use std::io;
+
+    let lex = ~Lexer::new(rules());
     let out = &mut io::stdio::stdout() as &mut io::Writer;
  //   ::automata::to_dot(lex.auto, out);
 
-    lex.write(None, out);
+    lex.write(None, out).unwrap();
 }  
+
+#[test]
+fn deterministic_output() {
+    use std::io::mem::MemWriter;
+
+    let gen = || {
+        let lex = Lexer::new(rules());
+        let mut out = MemWriter::new();
+        lex.write(None, &mut out as &mut ::std::io::Writer).unwrap();
+        out.inner()
+    };
+
+    // states are numbered from the initial state of the first condition
+    let first = gen();
+    assert_eq!(first, gen());
+    assert!(::std::str::from_utf8(first).contains("Condition::Initial => 1,"));
+}
+
+#[test]
+fn table_types() {
+    use rustlex::lexer::{TableType, AutoType, U8Type, U32Type};
+    use std::io::mem::MemWriter;
+
+    let gen = |regexps: ~[(~str, ~str, Option<~str>)], ty: TableType| {
+        let mut lex = Lexer::new(regexps);
+        lex.set_table_type(ty);
+        let mut out = MemWriter::new();
+
+        lex.write(None, &mut out as &mut ::std::io::Writer).map(|_| {
+            ::std::str::from_utf8(out.inner()).to_owned()
+        })
+    };
+
+    // a single literal of 300 bytes needs more states than a u8 can number
+    let long = ~[("a".repeat(300), ~"", None)];
+
+    assert!(gen(rules(), AutoType).unwrap().contains("static TRANSITION_TABLE: [[u8; 256];"));
+    assert!(gen(long.clone(), AutoType).unwrap().contains("static TRANSITION_TABLE: [[u16; 256];"));
+    assert!(gen(long.clone(), U32Type).unwrap().contains("static TRANSITION_TABLE: [[u32; 256];"));
+    assert!(gen(long, U8Type).is_err());
+}
+
+#[test]
+fn direct_coded() {
+    use rustlex::lexer::DirectCoded;
+
+    // both backends must give the same tokens, errors and spans
+    let main = "fn main() {\n\
+            for input in [\"\", \"abc 123\", \"a1b2\\tc3\\n\", \"x?y 42!\", \"007 \\u{e9}z\"] {\n\
+                for t in Lexer::new(input.as_bytes()) {\n\
+                    println!(\"{:?}\", t.map(|t| (t.token, t.span)));\n\
+                }\n\
+            }\n\
+        }";
+
+    let table = compile_generated(&token_lexer(main), "generated_table.rs", true);
+
+    let mut lex = token_lexer(main);
+    lex.set_backend(DirectCoded);
+    let direct = compile_generated(&lex, "generated_direct.rs", true);
+
+    assert!(!table.is_empty());
+    assert_eq!(table, direct);
+}
+
+// writes `lex` to `fname` in the temporary directory and compiles it with
+// the Rust compiler given by the RUSTLEX_RUSTC environment variable (`rustc`
+// by default), which must be a current stable one. if `run` is true, the
+// lexer is compiled along with the `main` function of its epilogue and run,
+// and must exit successfully, and its standard output is returned.
+// otherwise it is compiled as a library
+fn compile_generated(lex: &Lexer, fname: &str, run: bool) -> ~str {
+    use std::io::File;
+    use std::io::fs;
+    use std::os;
+    use std::run;
+
+    let pth = os::tmpdir().join(fname);
+    let output = os::tmpdir().join(fname + ".out");
+    lex.write(None, &mut File::create(&pth).unwrap() as &mut ::std::io::Writer).unwrap();
+
+    let rustc = match os::getenv("RUSTLEX_RUSTC") {
+        Some(r) => r,
+        None => ~"rustc"
+    };
+
+    let crate_type = if run { ~"bin" } else { ~"lib" };
+    let res = run::process_output(rustc, [~"--edition", ~"2021", ~"--crate-type",
+        crate_type, ~"-o", output.as_str().unwrap().to_owned(),
+        pth.as_str().unwrap().to_owned()]).unwrap();
+
+    fs::unlink(&pth);
+    assert!(res.status.success(), ::std::str::from_utf8(res.error).to_owned());
+
+    if run {
+        let res = run::process_output(output.as_str().unwrap(), []).unwrap();
+        fs::unlink(&output);
+        assert!(res.status.success(), ::std::str::from_utf8(res.error).to_owned());
+        ::std::str::from_utf8(res.output).to_owned()
+    } else {
+        fs::unlink(&output);
+        ~""
+    }
+}
+
+// a lexer for numbers and identifiers separated by whitespace, returning
+// typed tokens. `main` is put in the epilogue of the generated code
+fn token_lexer(main: &str) -> Lexer {
+    let mut regexps = ~[];
+    regexps.push((~"[0-9]+", ~"return Some(Tok::Number(self.lexeme().parse().unwrap()));", None));
+    regexps.push((~"[a-z]+", ~"return Some(Tok::Ident(self.lexeme().into_owned()));", None));
+    regexps.push((~"[ \t\n]", ~"", None));
+
+    let mut lex = Lexer::new(regexps);
+    lex.set_token_type(~"Tok");
+    lex.set_prologue(~"#![deny(warnings)]\n\
+                      #[derive(Debug, PartialEq)]\n\
+                      pub enum Tok { Number(u64), Ident(String) }");
+    lex.set_epilogue(main.to_owned());
+    lex
+}
+
+#[test]
+fn generated_code_compiles() {
+    use rustlex::lexer::DirectCoded;
+    use rustlex::lexer::PushInput;
+    use rustlex::lexer::SliceInput;
+
+    let mut lex = Lexer::new(rules());
+    lex.set_prologue(~"#![deny(warnings)]");
+    compile_generated(&lex, "generated_table_driven.rs", false);
+
+    lex.set_backend(DirectCoded);
+    lex.set_tracing(true);
+    compile_generated(&lex, "generated_direct_coded.rs", false);
+
+    lex.set_input(SliceInput);
+    compile_generated(&lex, "generated_slice.rs", false);
+
+    lex.set_input(PushInput);
+    compile_generated(&lex, "generated_push.rs", false);
+
+    let lex = token_lexer("pub fn tokens(s: &str) -> Vec<Result<Spanned<Tok>, LexError>> {\n    \
+                           Lexer::new(s.as_bytes()).collect()\n}");
+    compile_generated(&lex, "generated_tokens.rs", false);
+}
+
+#[test]
+fn generated_spans() {
+    let lex = token_lexer("fn main() {\n\
+        let toks: Vec<_> = Lexer::new(\"ab 12\\n\\t\\u{e9}\".as_bytes()).collect();\n\
+        let t = toks[1].as_ref().unwrap();\n\
+        assert_eq!(t.token, Tok::Number(12));\n\
+        assert_eq!((t.span.start.offset, t.span.end.offset), (3, 5));\n\
+        assert_eq!((t.span.end.line, t.span.end.column), (1, 6));\n\
+        match toks[2] {\n\
+            Err(LexError::NoMatch(0xc3, pos)) => assert_eq!((pos.line, pos.column), (2, 9)),\n\
+            _ => panic!()\n\
+        }\n\
+    }");
+
+    compile_generated(&lex, "generated_spans.rs", true);
+}
+
+#[test]
+fn generated_buffering() {
+    // lexemes longer than the input buffer, read one byte at a time, with
+    // the last one ending at the end of the input
+    let lex = token_lexer("struct Trickle<'a>(&'a [u8]);\n\
+        impl<'a> ::std::io::Read for Trickle<'a> {\n\
+            fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize> {\n\
+                if self.0.is_empty() || buf.is_empty() { return Ok(0); }\n\
+                buf[0] = self.0[0];\n\
+                self.0 = &self.0[1..];\n\
+                Ok(1)\n\
+            }\n\
+        }\n\
+        fn main() {\n\
+            let input = \"x\".repeat(1000) + \" 123\";\n\
+            let toks: Vec<_> = Lexer::new(Trickle(input.as_bytes())).map(|t| t.unwrap()).collect();\n\
+            assert_eq!(toks.len(), 2);\n\
+            assert_eq!(toks[0].token, Tok::Ident(\"x\".repeat(1000)));\n\
+            assert_eq!(toks[1].token, Tok::Number(123));\n\
+            assert_eq!(toks[1].span.end.offset, 1004);\n\
+        }");
+
+    compile_generated(&lex, "generated_buffering.rs", true);
+}
+
+#[test]
+fn generated_slice_lexer() {
+    use rustlex::lexer::SliceInput;
+
+    let mut regexps = ~[];
+    regexps.push((~"[0-9]+", ~"return Some(Tok::Number(self.lexeme_bytes()));", None));
+    regexps.push((~"[a-z]+", ~"return Some(Tok::Ident(self.lexeme()));", None));
+    regexps.push((~" ", ~"", None));
+
+    let mut lex = Lexer::new(regexps);
+    lex.set_input(SliceInput);
+    lex.set_token_type(~"Tok<'a>");
+    lex.set_prologue(~"#![deny(warnings)]\n\
+        use std::borrow::Cow;\n\
+        #[derive(Debug, PartialEq)]\n\
+        pub enum Tok<'a> { Number(&'a [u8]), Ident(Cow<'a, str>) }");
+
+    // the lexemes must point into the input
+    lex.set_epilogue(~"fn main() {\n\
+            let input = String::from(\"ab 12\");\n\
+            let toks: Vec<_> = Lexer::from_str(&input).map(|t| t.unwrap()).collect();\n\
+            match (&toks[0].token, &toks[1].token) {\n\
+                (&Tok::Ident(Cow::Borrowed(id)), &Tok::Number(n)) => {\n\
+                    assert_eq!(id.as_ptr(), input.as_ptr());\n\
+                    assert_eq!(n.as_ptr(), input[3..].as_ptr());\n\
+                    assert_eq!(n, b\"12\");\n\
+                }\n\
+                _ => panic!()\n\
+            }\n\
+        }");
+
+    compile_generated(&lex, "generated_slice_lexer.rs", true);
+}
+
+#[test]
+fn generated_push_lexer() {
+    use rustlex::lexer::PushInput;
+
+    let mut lex = token_lexer("fn main() {\n\
+            let mut lexer = Lexer::new();\n\
+            assert!(lexer.feed(b\"ab\").is_empty());\n\
+            let toks = lexer.feed(b\"c 1\");\n\
+            assert_eq!(toks.len(), 1);\n\
+            assert_eq!(toks[0].as_ref().unwrap().token, Tok::Ident(String::from(\"abc\")));\n\
+            assert!(lexer.feed(b\"23\").is_empty());\n\
+            let toks = lexer.finish();\n\
+            assert_eq!(toks.len(), 1);\n\
+            let t = toks[0].as_ref().unwrap();\n\
+            assert_eq!(t.token, Tok::Number(123));\n\
+            assert_eq!((t.span.start.offset, t.span.end.offset), (4, 7));\n\
+            assert!(lexer.finish().is_empty());\n\
+        }");
+
+    lex.set_input(PushInput);
+    compile_generated(&lex, "generated_push_lexer.rs", true);
+}
+
+#[test]
+fn template_errors() {
+    use std::io::mem::MemWriter;
+
+    let lex = Lexer::new(rules());
+    let mut out = MemWriter::new();
+    let res = lex.write(Some(~"no/such/template.rst"), &mut out as &mut ::std::io::Writer);
+    assert!(res.is_err());
+
+    let lazy = Lexer::new_lazy(rules());
+    assert!(lazy.write(None, &mut out as &mut ::std::io::Writer).is_err());
+}
+
+#[test]
+fn template_placeholders() {
+    use rustlex::template::parse_template;
+    use std::io::File;
+    use std::io::mem::MemWriter;
+    use std::io::fs;
+
+    let required = "#RUSTLEX_TRANSITION_TABLE\n#RUSTLEX_ACCEPTING_TABLE\n\
+                    #RUSTLEX_CONDITION_ENUM\n#RUSTLEX_STATE_ACTIONS\n";
+
+    assert!(parse_template(required).is_ok());
+    assert!(parse_template("#RUSTLEX_TRANSITION_TABLE\n").is_err());
+    assert!(parse_template(required + "struct #RUSTLEX_LEXER {}\n").is_err());
+
+    // the last line of the template has no newline, and neither must the code
+    let pth = ::std::os::tmpdir().join("template_placeholders.rst");
+    File::create(&pth).write_str(required +
+        "struct #RUSTLEX_LEXER_TYPE { st: #RUSTLEX_STATE_TYPE }\n#RUSTLEX_EPILOGUE\n// end");
+
+    let mut lex = Lexer::new(rules());
+    lex.set_lexer_type(~"MyLexer");
+    lex.set_epilogue(~"fn main() {}");
+
+    let mut out = MemWriter::new();
+    let res = lex.write(Some(pth.as_str().unwrap().to_owned()),
+        &mut out as &mut ::std::io::Writer);
+    fs::unlink(&pth);
+    res.unwrap();
+
+    let code = ::std::str::from_utf8_owned(out.inner());
+    assert!(code.contains("enum Condition {"));
+    assert!(code.ends_with("struct MyLexer { st: u8 }\nfn main() {}\n// end"));
+}
+
+#[test]
+fn lazy_matcher() {
+    let lex = Lexer::new_lazy(rules());
+
+    for &size in [1u, 100u].iter() {
+        let mut m = lex.lazy_matcher("Initial", size).unwrap();
+
+        assert_eq!(m.longest_match(bytes!("abbc")), Some((2u, 3u)));
+        assert_eq!(m.longest_match(bytes!("aab")), Some((3u, 3u)));
+        assert_eq!(m.longest_match(bytes!("ac")), Some((1u, 1u)));
+        assert_eq!(m.longest_match(bytes!("abbbbbc")), Some((3u, 6u)));
+        assert_eq!(m.longest_match(bytes!("c")), None);
+    }
+
+    assert!(lex.lazy_matcher("Bar", 100).is_none());
+
+    // each byte of a long literal leads to a new DFA state. a cache too
+    // small to hold them forces the matcher to fall back to NFA simulation,
+    // which must give the same results
+    let mut regexps = ~[];
+    regexps.push((~"abcdef", ~"", None));
+    regexps.push((~"[a-z]+", ~"", None));
+    let lex = Lexer::new_lazy(regexps);
+
+    for &size in [1u, 100u].iter() {
+        let mut m = lex.lazy_matcher("Initial", size).unwrap();
+
+        assert_eq!(m.longest_match(bytes!("abcdef ")), Some((1u, 6u)));
+        assert_eq!(m.longest_match(bytes!("abcdefgh")), Some((2u, 8u)));
+        assert_eq!(m.longest_match(bytes!("abc1")), Some((2u, 3u)));
+        assert_eq!(m.fallbacks() > 0, size == 1);
+    }
+}
+
+#[test]
+fn interpreter() {
+    use rustlex::interp::Token;
+
+    let lex = Lexer::new(rules());
+    let mut interp = lex.interpreter();
+    let toks = interp.tokenize(bytes!("abbcaab"));
+
+    assert_eq!(toks, ~[
+        Token { rule: 2, start: 0, end: 3 },
+        Token { rule: 0, start: 3, end: 4 },
+        Token { rule: 3, start: 4, end: 7 }
+    ]);
+
+    let mut seen = ~[];
+    assert!(interp.set_condition("Foo"));
+    interp.run(bytes!("bba"), |_, tok, lexeme| {
+        seen.push((tok.rule, ::std::str::from_utf8(lexeme).to_owned()));
+    });
+
+    assert_eq!(seen, ~[(4u, ~"bb"), (0u, ~"a")]);
+}
+
+#[test]
+fn nfa_simulation() {
+    let lex = Lexer::new(rules());
+
+    assert_eq!(lex.simulate("Initial", bytes!("abbc")), Some((2u, 3u)));
+    assert_eq!(lex.simulate("Foo", bytes!("abbc")), Some((4u, 3u)));
+    assert_eq!(lex.simulate("Foo", bytes!("c")), None);
+
+    // the determinized and minimized automata must agree with the NFA
+    let input = bytes!("aabbbabbaabcabab", 0xffu8);
+    assert_eq!(lex.check_against_nfa("Initial", input), None);
+    assert_eq!(lex.check_against_nfa("Foo", input), None);
+}
+
+#[test]
+fn shadowed_rules() {
+    use rustlex::lexer::Options;
+
+    let mut regexps = ~[];
+    regexps.push((~"[a-z]+", ~"", None));
+    regexps.push((~"if", ~"", None));
+
+    let mut opts = Options::new();
+    opts.shadowed_rules_are_errors = true;
+
+    match Lexer::with_options(regexps, &opts) {
+        Ok(_) => fail!("rule 2 should be reported as shadowed"),
+        Err(errs) => {
+            assert_eq!(errs.len(), 1);
+            assert!(errs[0].contains("rule 2 (if)"));
+            assert!(errs[0].contains("by rule 1 ([a-z]+)"));
+        }
+    }
+}
+
+#[test]
+fn empty_rules() {
+    use rustlex::lexer::Options;
+
+    let mut regexps = ~[];
+    regexps.push((~"a*", ~"", None));
+    regexps.push((~"b|(cd)*", ~"", None));
+    regexps.push((~"ab*", ~"", None));
+
+    let mut opts = Options::new();
+    opts.empty_rules_are_errors = true;
+
+    match Lexer::with_options(regexps, &opts) {
+        Ok(_) => fail!("rules 1 and 2 should be reported as nullable"),
+        Err(errs) => assert_eq!(errs, ~[
+            ~"rule 1 (a*) matches the empty string",
+            ~"rule 2 (b|(cd)*) matches the empty string"
+        ])
+    }
+}
+
+#[test]
+fn ambiguities() {
+    use rustlex::lexer::Ambiguity;
+
+    let mut regexps = ~[];
+    regexps.push((~"if", ~"", None));
+    regexps.push((~"[a-z]+", ~"", None));
+    regexps.push((~"[0-9]+", ~"", None));
+
+    let lex = Lexer::new(regexps);
+    assert_eq!(lex.ambiguities(), ~[Ambiguity {
+        condition: ~"Initial",
+        first: 1,
+        second: 2,
+        example: bytes!("if").to_owned()
+    }]);
+}
+
+#[test]
+fn witnesses() {
+    let lex = Lexer::new(rules());
+    let witnesses = lex.witnesses(3);
+    let mut interp = lex.interpreter();
+
+    assert_eq!(witnesses[0], (1u, ~"Initial", ~[bytes!("a").to_owned()]));
+    assert_eq!(witnesses[1], (2u, ~"Initial", ~[bytes!("abb").to_owned()]));
+
+    // each witness must be tokenized as a single lexeme of its rule
+    for &(rule, ref cond, ref inputs) in witnesses.iter() {
+        assert!(interp.set_condition(*cond));
+        assert!(inputs.len() >= 1 && inputs.len() <= 3);
+
+        for input in inputs.iter() {
+            let toks = interp.tokenize(*input);
+            assert_eq!(toks.len(), 1);
+            assert_eq!(toks[0].rule, rule);
+        }
+    }
+}
+
+#[test]
+fn fuzzer() {
+    use std::rand::{XorShiftRng, SeedableRng};
+
+    let lex = Lexer::new(rules());
+    let mut fuzzer = lex.fuzzer("Initial").unwrap();
+
+    // seeded so that the coverage reached is always the same
+    let mut rng: XorShiftRng = SeedableRng::from_seed([1u32, 2, 3, 4]);
+    let mut interp = lex.interpreter();
+
+    for _ in range(0, 1000) {
+        let input = fuzzer.gen_input(&mut rng, 5);
+        assert_eq!(lex.check_against_nfa("Initial", input), None);
+        interp.tokenize(input);
+    }
+
+    let (covered, total) = fuzzer.coverage();
+    assert_eq!(covered, total);
+    assert!(fuzzer.unvisited_states().is_empty());
+}
+
+#[test]
+fn lazy_analyses() {
+    use std::task;
+
+    // the analyses need the DFA, which lazy lexers don't have
+    assert!(Lexer::new_lazy(rules()).fuzzer("Initial").is_none());
+    assert!(task::try(proc() { Lexer::new_lazy(rules()).witnesses(1); }).is_err());
+    assert!(task::try(proc() { Lexer::new_lazy(rules()).uncovered_inputs(); }).is_err());
+    assert!(task::try(proc() { Lexer::new_lazy(rules()).backing_up(); }).is_err());
+}
+
+#[test]
+fn equivalence() {
+    let mut old = ~[];
+    old.push((~"if", ~"kw", None));
+    old.push((~"else", ~"kw", None));
+    old.push((~"[a-z]+", ~"ident", None));
+
+    let mut new = ~[];
+    new.push((~"if|else", ~"kw", None));
+    new.push((~"[a-z]+", ~"ident", None));
+
+    let old = Lexer::new(old);
+    let new = Lexer::new(new);
+    assert_eq!(old.compare(&new), None);
+
+    let mut smaller = ~[];
+    smaller.push((~"if", ~"kw", None));
+    smaller.push((~"[a-z]", ~"ident", None));
+
+    let smaller = Lexer::new(smaller);
+    assert_eq!(smaller.included_in(&new), None);
+
+    let diff = new.compare(&smaller).unwrap();
+    assert_eq!(diff.condition, ~"Initial");
+    assert_eq!(diff.input, bytes!("aa").to_owned());
+    assert_eq!((diff.left, diff.right), (Some(2u), None));
+}
+
+#[test]
+fn lazy_equivalence() {
+    use std::task;
+
+    assert!(task::try(proc() {
+        Lexer::new_lazy(rules()).compare(&Lexer::new(rules()));
+    }).is_err());
+
+    assert!(task::try(proc() {
+        Lexer::new(rules()).included_in(&Lexer::new_lazy(rules()));
+    }).is_err());
+
+    assert!(task::try(proc() {
+        Lexer::new_lazy(rules()).diff(&Lexer::new_lazy(rules()), 1);
+    }).is_err());
+}
+
+#[test]
+fn spec_diff() {
+    use rustlex::spec::parse_spec;
+    use std::io::mem::MemWriter;
+
+    let old = parse_spec("
+        # keywords
+        %token      Tok
+        if          kw
+        [a-z]+      ident
+        <Str>[a-z]  chr
+    ").unwrap();
+
+    assert_eq!(old.token_type, Some(~"Tok"));
+    assert_eq!(old.rules[2], (~"[a-z]", ~"chr", Some(~"Str")));
+
+    let new = parse_spec("
+        if|else     kw
+        [a-z]+      ident
+    ").unwrap();
+
+    assert_eq!(new.token_type, None);
+    assert!(parse_spec("%token\n").is_err());
+    assert!(parse_spec("%tokens Tok\n").is_err());
+    assert!(parse_spec("%tokenTok\n").is_err());
+    assert_eq!(parse_spec("%token\tTok\n").unwrap().token_type, Some(~"Tok"));
+
+    let old = Lexer::from_spec(old);
+    let new = Lexer::from_spec(new);
+    let diff = old.diff(&new, 1);
+
+    assert_eq!(diff.len(), 2);
+    assert_eq!(diff[0], (~"Initial", Some(2u), Some(1u),
+        ~[bytes!("else").to_owned()]));
+    assert_eq!(diff[1], (~"Str", Some(3u), None, ~[bytes!("a").to_owned()]));
+
+    // the declared token type is the one of the generated lexer
+    let mut out = MemWriter::new();
+    old.write(None, &mut out as &mut ::std::io::Writer).unwrap();
+    assert!(::std::str::from_utf8(out.inner()).contains("-> Option<Tok> {"));
+}
+
+#[test]
+fn coverage() {
+    use rustlex::lexer::Options;
+    use rustlex::lexer::Uncovered;
+
+    let mut regexps = ~[];
+    regexps.push((~"ab", ~"", None));
+    regexps.push((~"[b-z]", ~"", None));
+
+    let lex = Lexer::new(regexps.clone());
+    let uncovered = lex.uncovered_inputs();
+
+    assert_eq!(uncovered.len(), 2);
+    assert_eq!(uncovered[0], Uncovered {
+        condition: ~"Initial",
+        prefix: ~[],
+        bytes: ~[(0u8, 0x60u8), (0x7bu8, 0xffu8)],
+        at_eof: false
+    });
+    assert_eq!(uncovered[1].to_str(),
+        ~"Initial: \"a\" followed by [\\x00-ac-\\xff] or at end of input");
+
+    // a catch-all rule covers every input
+    regexps.push((~".", ~"", None));
+    let mut opts = Options::new();
+    opts.require_full_coverage = true;
+    assert!(Lexer::with_options(regexps, &opts).is_ok());
+}
+
+#[test]
+fn backing_up() {
+    use rustlex::diagnostics::CollectedDiagnostics;
+    use rustlex::diagnostics::Warning;
+    use rustlex::lexer::Options;
+
+    let mut regexps = ~[];
+    regexps.push((~"a", ~"", None));
+    regexps.push((~"a*b", ~"", None));
+
+    let lex = Lexer::new(regexps);
+    let states = lex.backing_up();
+
+    // after "a", more "a"s lead to a non-accepting state that loops until
+    // a "b" is found
+    assert_eq!(states.len(), 1);
+    assert_eq!(states[0].example, bytes!("aa").to_owned());
+    assert_eq!(states[0].bytes, ~[(0u8, 0x60u8), (0x63u8, 0xffu8)]);
+    assert_eq!(states[0].loop_input, Some(bytes!("a").to_owned()));
+
+    // the quadratic rescanning is only reported when asked for
+    let warnings = |warn: bool| {
+        let mut regexps = ~[];
+        regexps.push((~"a", ~"", None));
+        regexps.push((~"a*b", ~"", None));
+
+        let mut opts = Options::new();
+        opts.warn_quadratic_rescanning = warn;
+        let mut diag = CollectedDiagnostics::new();
+        assert!(Lexer::with_diagnostics(regexps, &opts, &mut diag).is_ok());
+        diag.diagnostics.iter().filter(|d| d.level == Warning).count()
+    };
+
+    assert_eq!(warnings(false), 0);
+    assert_eq!(warnings(true), 1);
+
+    // after backing up to "a", the scan of "bbb..." stops at the first "b",
+    // so the loop doesn't make the scanning time quadratic
+    let mut regexps = ~[];
+    regexps.push((~"a", ~"", None));
+    regexps.push((~"ab*c", ~"", None));
+
+    let mut opts = Options::new();
+    opts.warn_quadratic_rescanning = true;
+    let mut diag = CollectedDiagnostics::new();
+    let lex = Lexer::with_diagnostics(regexps, &opts, &mut diag).unwrap();
+
+    let states = lex.backing_up();
+    assert_eq!(states.len(), 1);
+    assert_eq!(states[0].example, bytes!("ab").to_owned());
+    assert_eq!(states[0].loop_input, None);
+    assert!(diag.diagnostics.iter().all(|d| d.level != Warning));
+}
+
+#[test]
+fn stats() {
+    let lex = Lexer::new(rules());
+    let stats = lex.stats();
+
+    assert_eq!(stats.conditions.len(), 2);
+    assert_eq!(stats.conditions[0].name, ~"Initial");
+    assert_eq!(stats.conditions[1].name, ~"Foo");
+    assert!(stats.conditions[0].minimized_dfa_states <= stats.conditions[0].dfa_states);
+
+    let tables = stats.tables.unwrap();
+
+    // 'a', 'b' and every other byte
+    assert_eq!(tables.equivalence_classes, 3);
+    assert_eq!(tables.transition_table, Some(("u8", tables.states * 256)));
+    assert_eq!(tables.accepting_table, Some(("u8", tables.states)));
+    assert!(tables.direct_coded_ranges > 0);
+    assert!(tables.direct_coded_accepting > 0);
+}
+
+#[test]
+fn diagnostics() {
+    use rustlex::diagnostics::CollectedDiagnostics;
+    use rustlex::diagnostics::Warning;
+    use rustlex::lexer::Options;
+
+    let mut regexps = ~[];
+    regexps.push((~"[a-z]+", ~"", None));
+    regexps.push((~"if", ~"", None));
+
+    let mut diag = CollectedDiagnostics::new();
+    assert!(Lexer::with_diagnostics(regexps, &Options::new(), &mut diag).is_ok());
+
+    let warnings: ~[&rustlex::diagnostics::Diagnostic] = diag.diagnostics.iter()
+        .filter(|d| d.level == Warning).collect();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].rule, Some(2u));
+    assert_eq!(warnings[0].condition, Some(~"Initial"));
+}
@@ -2,6 +2,7 @@ use automata::Automata;
 use automata::AutomataState;
 use dfa::DFA;
 use std::hashmap::HashMap;
+use std::hashmap::HashSet;
 
 /*
  * Converts a set of deterministic automatas into their transition-table
@@ -30,11 +31,43 @@ pub fn transition_table(a: &[~DFA]) -> (~[~[uint]], ~[uint], ~HashMap<uint, uint
     while automata < a.len() {
         let au = &a[automata];
 
-        for (i, _) in au.states_iter() {
+        /*
+         * The states of each automata are numbered in breadth-first order
+         * from its initial state, following transitions by increasing byte.
+         * This doesn't depend on the IDs the states were given while
+         * building the automata, so the tables emitted for the same rules
+         * are always identical. States that can't be reached, if any, are
+         * numbered afterwards by increasing ID.
+         */
+        let mut order = ~[au.initial()];
+        let mut next = 0u;
+
+        // the states already in `order`
+        let mut seen = HashSet::new();
+        seen.insert(au.initial());
+
+        while next < order.len() {
+            let st = order[next];
+            next += 1;
+
+            for c in range(0u, 256) {
+                match au.transition(st, c as u8) {
+                    Some(dst) => if seen.insert(dst) { order.push(dst) },
+                    None => ()
+                }
+            }
+        }
+
+        let mut unreachable: ~[uint] = au.states_iter()
+            .map(|(i, _)| *i)
+            .filter(|i| !seen.contains(i))
+            .collect();
+        unreachable.sort();
+
+        for i in order.iter().chain(unreachable.iter()) {
             new_indexes.insert(*i, current_index); 
 
             // also remember which automata this states belongs to
-            println!("ID {:u} from automata {:u} is now {:u}", *i, automata, current_index);
             old_indexes.insert(current_index, (*i, automata));
             current_index += 1;
         }
@@ -46,7 +79,6 @@ pub fn transition_table(a: &[~DFA]) -> (~[~[uint]], ~[uint], ~HashMap<uint, uint
         let mut trans_table = ~[0, ..256];
 
         if i != 0 {
-            println!("attempting to access new index {:u}", i);
             let &(old_idx, au) = old_indexes.find(&i).unwrap();
             let au = &a[au];
             let st = au.find_state(old_idx).unwrap();